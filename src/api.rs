@@ -1,2 +1,8 @@
+pub mod accounts;
 pub mod courses;
+pub mod files;
+pub mod folders;
+pub mod graphql;
+pub mod progress;
+pub mod request;
 pub mod users;
@@ -0,0 +1,329 @@
+use super::request::{Async, ListRequest};
+use crate::{
+    encoding::RequestBody,
+    ids::{AccountId, TermId, UserId},
+    models::{accounts::Account, courses::Course},
+    params::{
+        AccountCourseSort, AccountCourseState, AccountInclude, CourseEnrollmentType, CourseInclude,
+        CourseSearchBy, Order,
+    },
+    Canvas, CanvasResult, PaginatedVec,
+};
+
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct AccountHandler {
+    canvas: Canvas,
+}
+impl AccountHandler {
+    pub fn new(canvas: Canvas) -> Self {
+        Self { canvas }
+    }
+}
+
+impl AccountHandler {
+    /// Get a specific account.
+    pub async fn get(&self, account_id: impl Into<AccountId>) -> CanvasResult<Account> {
+        let account_id = account_id.into().to_path_segment();
+        self.canvas
+            .get_endpoint(&format!("accounts/{account_id}"), None)
+            .await
+    }
+
+    /// List the accounts the current user can view or manage, typically the
+    /// ones they are an admin of.
+    pub fn list(&self) -> ListAccounts {
+        ListRequest::new(self.canvas.clone(), "accounts".to_string())
+    }
+
+    /// List the sub-accounts directly below an account, or all of them with
+    /// [`ListSubAccounts::recursive`].
+    pub fn sub_accounts(&self, account_id: impl Into<AccountId>) -> ListSubAccounts {
+        let account_id = account_id.into().to_path_segment();
+        ListRequest::new(
+            self.canvas.clone(),
+            format!("accounts/{account_id}/sub_accounts"),
+        )
+    }
+
+    /// Stream an account and every account below it, level by level, so that
+    /// each account comes after its parent.
+    ///
+    /// Unlike with [`ListSubAccounts::recursive`], whose order Canvas leaves
+    /// open, the tree can be rebuilt as it comes in through
+    /// `parent_account_id`.
+    pub fn walk(&self, account_id: impl Into<AccountId>) -> PaginatedVec<CanvasResult<Account>> {
+        use async_stream::stream;
+
+        let handler = self.clone();
+        let account_id = account_id.into();
+
+        Box::pin(stream! {
+            let mut queue = match handler.get(account_id).await {
+                Ok(account) => {
+                    let queue = VecDeque::from([account.id]);
+                    yield Ok(account);
+                    queue
+                }
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            while let Some(parent_id) = queue.pop_front() {
+                let mut children = handler.sub_accounts(parent_id).send().await;
+                while let Some(child) = children.next().await {
+                    if let Ok(child) = &child {
+                        queue.push_back(child.id);
+                    }
+                    yield child;
+                }
+            }
+        })
+    }
+
+    /// List the courses of an account and its sub-accounts.
+    pub fn courses(&self, account_id: impl Into<AccountId>) -> ListAccountCourses {
+        let account_id = account_id.into().to_path_segment();
+        ListRequest::new(
+            self.canvas.clone(),
+            format!("accounts/{account_id}/courses"),
+        )
+    }
+
+    /// Get the settings of an account, such as `restrict_student_past_view`.
+    ///
+    /// Which settings exist depends on the Canvas version and the features
+    /// enabled, so they are returned as they come.
+    pub async fn settings(
+        &self,
+        account_id: impl Into<AccountId>,
+    ) -> CanvasResult<Map<String, Value>> {
+        let account_id = account_id.into().to_path_segment();
+        self.canvas
+            .get_endpoint(&format!("accounts/{account_id}/settings"), None)
+            .await
+    }
+
+    /// Change some settings of an account, leaving the others as they are.
+    ///
+    /// Lockable settings take an object, e.g.
+    /// `{"restrict_student_past_view": {"value": true, "locked": false}}`.
+    pub async fn update_settings(
+        &self,
+        account_id: impl Into<AccountId>,
+        settings: Map<String, Value>,
+    ) -> CanvasResult<Account> {
+        #[derive(Serialize)]
+        struct Params {
+            account: Settings,
+        }
+        #[derive(Serialize)]
+        struct Settings {
+            settings: Map<String, Value>,
+        }
+
+        let account_id = account_id.into().to_path_segment();
+        let params = Params {
+            account: Settings { settings },
+        };
+        self.canvas
+            .put_endpoint(
+                &format!("accounts/{account_id}"),
+                RequestBody::json(&params)?,
+            )
+            .await
+    }
+}
+
+pub type ListAccounts<M = Async> = ListRequest<Account, ListAccountsParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListAccountsParams {
+    include: Vec<AccountInclude>,
+}
+
+impl<M> ListAccounts<M> {
+    /// Additional information to include with each account.
+    pub fn include(mut self, include: impl IntoIterator<Item = AccountInclude>) -> Self {
+        self.params.include.extend(include);
+        self
+    }
+}
+
+pub type ListSubAccounts<M = Async> = ListRequest<Account, ListSubAccountsParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListSubAccountsParams {
+    recursive: Option<bool>,
+}
+
+impl<M> ListSubAccounts<M> {
+    /// Return every account below the account instead of just its children.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.params.recursive = Some(recursive);
+        self
+    }
+}
+
+pub type ListAccountCourses<M = Async> = ListRequest<Course, ListAccountCoursesParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListAccountCoursesParams {
+    with_enrollments: Option<bool>,
+    enrollment_type: Vec<CourseEnrollmentType>,
+    published: Option<bool>,
+    completed: Option<bool>,
+    blueprint: Option<bool>,
+    blueprint_associated: Option<bool>,
+    public: Option<bool>,
+    by_teachers: Vec<UserId>,
+    by_subaccounts: Vec<AccountId>,
+    homeroom: Option<bool>,
+    state: Vec<AccountCourseState>,
+    enrollment_term_id: Option<TermId>,
+    search_term: Option<String>,
+    search_by: Option<CourseSearchBy>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    starts_before: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    ends_after: Option<OffsetDateTime>,
+    include: Vec<CourseInclude>,
+    sort: Option<AccountCourseSort>,
+    order: Option<Order>,
+}
+
+impl<M> ListAccountCourses<M> {
+    /// Only return courses with (`true`) or without (`false`) enrollments.
+    pub fn with_enrollments(mut self, with_enrollments: bool) -> Self {
+        self.params.with_enrollments = Some(with_enrollments);
+        self
+    }
+
+    /// Only return courses with at least one enrollment of these types.
+    pub fn enrollment_type(
+        mut self,
+        enrollment_type: impl IntoIterator<Item = CourseEnrollmentType>,
+    ) -> Self {
+        self.params.enrollment_type.extend(enrollment_type);
+        self
+    }
+
+    /// Only return published (`true`) or unpublished (`false`) courses.
+    pub fn published(mut self, published: bool) -> Self {
+        self.params.published = Some(published);
+        self
+    }
+
+    /// Only return courses that have (`true`) or have not (`false`) ended.
+    pub fn completed(mut self, completed: bool) -> Self {
+        self.params.completed = Some(completed);
+        self
+    }
+
+    /// Only return Blueprint courses (`true`), or leave them out (`false`).
+    pub fn blueprint(mut self, blueprint: bool) -> Self {
+        self.params.blueprint = Some(blueprint);
+        self
+    }
+
+    /// Only return courses associated with a Blueprint course (`true`), or
+    /// leave them out (`false`).
+    pub fn blueprint_associated(mut self, associated: bool) -> Self {
+        self.params.blueprint_associated = Some(associated);
+        self
+    }
+
+    /// Only return public (`true`) or private (`false`) courses.
+    pub fn public(mut self, public: bool) -> Self {
+        self.params.public = Some(public);
+        self
+    }
+
+    /// Only return courses taught by one of these users.
+    pub fn by_teachers<U: Into<UserId>>(mut self, teachers: impl IntoIterator<Item = U>) -> Self {
+        self.params
+            .by_teachers
+            .extend(teachers.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only return courses in one of these sub-accounts.
+    pub fn by_subaccounts<A: Into<AccountId>>(
+        mut self,
+        accounts: impl IntoIterator<Item = A>,
+    ) -> Self {
+        self.params
+            .by_subaccounts
+            .extend(accounts.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only return homeroom courses.
+    pub fn homeroom(mut self, homeroom: bool) -> Self {
+        self.params.homeroom = Some(homeroom);
+        self
+    }
+
+    /// Only return courses in these states. Canvas leaves out deleted courses
+    /// by default.
+    pub fn state(mut self, state: impl IntoIterator<Item = AccountCourseState>) -> Self {
+        self.params.state.extend(state);
+        self
+    }
+
+    /// Only return courses in this enrollment term.
+    pub fn enrollment_term_id(mut self, term_id: impl Into<TermId>) -> Self {
+        self.params.enrollment_term_id = Some(term_id.into());
+        self
+    }
+
+    /// Only return courses whose name, code or SIS ID contains this term, or
+    /// whose teacher's name does with [`search_by`](Self::search_by). Canvas
+    /// requires at least three characters.
+    pub fn search_term(mut self, term: impl Into<String>) -> Self {
+        self.params.search_term = Some(term.into());
+        self
+    }
+
+    /// What the search term is matched against.
+    pub fn search_by(mut self, search_by: CourseSearchBy) -> Self {
+        self.params.search_by = Some(search_by);
+        self
+    }
+
+    /// Only return courses starting before this date, or without a start.
+    pub fn starts_before(mut self, date: OffsetDateTime) -> Self {
+        self.params.starts_before = Some(date);
+        self
+    }
+
+    /// Only return courses ending after this date, or without an end.
+    pub fn ends_after(mut self, date: OffsetDateTime) -> Self {
+        self.params.ends_after = Some(date);
+        self
+    }
+
+    /// Additional information to include with each course.
+    pub fn include(mut self, include: impl IntoIterator<Item = CourseInclude>) -> Self {
+        self.params.include.extend(include);
+        self
+    }
+
+    /// The column to sort the courses by.
+    pub fn sort(mut self, sort: AccountCourseSort) -> Self {
+        self.params.sort = Some(sort);
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.params.order = Some(order);
+        self
+    }
+}
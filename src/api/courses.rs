@@ -1,16 +1,37 @@
-use crate::{models::courses::Course, Canvas, CanvasResult, PaginatedVec};
-pub struct CourseHandler<'canvas> {
-    canvas: &'canvas Canvas,
+use super::{
+    files::{FileUpload, UploadSource},
+    request::{Async, ListRequest},
+};
+use crate::{
+    encoding::RequestBody,
+    error::CanvasError,
+    ids::{AccountId, CourseId, TermId, UserId},
+    models::{
+        courses::{Course, CourseFormat, PageType, WorkflowState},
+        progress::Progress,
+    },
+    params::{CourseEnrollmentState, CourseEnrollmentType, CourseInclude},
+    Canvas, CanvasResult,
+};
+
+use serde::Serialize;
+use serde_json::Value;
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct CourseHandler {
+    canvas: Canvas,
 }
-impl<'canvas> CourseHandler<'canvas> {
-    pub fn new(canvas: &'canvas Canvas) -> Self {
+impl CourseHandler {
+    pub fn new(canvas: Canvas) -> Self {
         Self { canvas }
     }
 }
 
-impl<'canvas> CourseHandler<'canvas> {
+impl CourseHandler {
     /// Get a specific course.
-    pub async fn get(self, course_id: u32) -> CanvasResult<Course> {
+    pub async fn get(self, course_id: impl Into<CourseId>) -> CanvasResult<Course> {
+        let course_id = course_id.into().to_path_segment();
         self.canvas
             .get_endpoint(&format!("courses/{course_id}"), None)
             .await
@@ -19,14 +40,519 @@ impl<'canvas> CourseHandler<'canvas> {
     /// List the current user's active courses.
     ///
     /// The current user is the one to which the API token belongs.
-    pub async fn list(&self) -> PaginatedVec<CanvasResult<Course>> {
-        self.canvas.stream_endpoint("courses").await
+    pub fn list(&self) -> ListCourses {
+        ListRequest::new(self.canvas.clone(), "courses".to_string())
+    }
+
+    /// Upload a file to the files of a course.
+    pub fn upload_file(&self, course_id: impl Into<CourseId>, source: UploadSource) -> FileUpload {
+        let course_id = course_id.into().to_path_segment();
+        self.canvas
+            .upload_file(format!("courses/{course_id}/files"), source)
+    }
+
+    /// Upload a file to attach to a submission of `user_id` for an
+    /// assignment. The file is then passed by ID when submitting.
+    pub fn upload_submission_file(
+        &self,
+        course_id: impl Into<CourseId>,
+        assignment_id: u64,
+        user_id: impl Into<UserId>,
+        source: UploadSource,
+    ) -> FileUpload {
+        let course_id = course_id.into().to_path_segment();
+        let user_id = user_id.into().to_path_segment();
+        self.canvas.upload_file(
+            format!("courses/{course_id}/assignments/{assignment_id}/submissions/{user_id}/files"),
+            source,
+        )
     }
 
     /// List the active courses for a specific user.
-    pub async fn list_for_user(&self, user_id: u32) -> PaginatedVec<CanvasResult<Course>> {
+    pub fn list_for_user(&self, user_id: impl Into<UserId>) -> ListUserCourses {
+        let user_id = user_id.into().to_path_segment();
+        ListRequest::new(self.canvas.clone(), format!("users/{user_id}/courses"))
+    }
+
+    /// Create a course in an account.
+    pub async fn create(
+        &self,
+        account_id: impl Into<AccountId>,
+        course: NewCourse,
+    ) -> CanvasResult<Course> {
+        let account_id = account_id.into().to_path_segment();
         self.canvas
-            .stream_endpoint(&format!("users/{user_id}/courses"))
+            .post_endpoint(
+                &format!("accounts/{account_id}/courses"),
+                RequestBody::form(&course)?,
+            )
             .await
     }
+
+    /// Change the settings of a course, and move it to another state with
+    /// `event`. Settings left unset stay as they are.
+    pub async fn update(
+        &self,
+        course_id: impl Into<CourseId>,
+        settings: CourseSettings,
+        event: Option<CourseEvent>,
+    ) -> CanvasResult<Course> {
+        #[derive(Serialize)]
+        struct Params {
+            course: Update,
+        }
+        #[derive(Serialize)]
+        struct Update {
+            #[serde(flatten)]
+            settings: CourseSettings,
+            event: Option<CourseEvent>,
+        }
+
+        let course_id = course_id.into().to_path_segment();
+        let params = Params {
+            course: Update { settings, event },
+        };
+        self.canvas
+            .put_endpoint(&format!("courses/{course_id}"), RequestBody::form(&params)?)
+            .await
+    }
+
+    /// Delete a course. Deleted courses can be restored with
+    /// [`CourseEvent::Undelete`].
+    pub async fn delete(&self, course_id: impl Into<CourseId>) -> CanvasResult<()> {
+        self.end(course_id, CourseEvent::Delete).await
+    }
+
+    /// Conclude a course, making it read-only for its students.
+    pub async fn conclude(&self, course_id: impl Into<CourseId>) -> CanvasResult<()> {
+        self.end(course_id, CourseEvent::Conclude).await
+    }
+
+    async fn end(&self, course_id: impl Into<CourseId>, event: CourseEvent) -> CanvasResult<()> {
+        #[derive(Serialize)]
+        struct Params {
+            event: CourseEvent,
+        }
+
+        // Canvas answers with e.g. `{"delete": true}`.
+        let course_id = course_id.into().to_path_segment();
+        let _: Value = self
+            .canvas
+            .delete_endpoint(
+                &format!("courses/{course_id}"),
+                RequestBody::form(&Params { event })?,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Delete all content of a course by replacing it with a new, empty
+    /// course with the same settings and enrollments, which is returned.
+    ///
+    /// The new course has a different ID.
+    pub async fn reset_content(&self, course_id: impl Into<CourseId>) -> CanvasResult<Course> {
+        let course_id = course_id.into().to_path_segment();
+        self.canvas
+            .post_endpoint(
+                &format!("courses/{course_id}/reset_content"),
+                RequestBody::Empty,
+            )
+            .await
+    }
+
+    /// Run `event` on many courses of an account at once.
+    ///
+    /// Canvas does this in the background, follow it with
+    /// [`ProgressHandler::watch`](crate::api::progress::ProgressHandler::watch).
+    /// [`CourseEvent::Claim`] is not supported here and fails with
+    /// [`CanvasError::Encoding`] before anything is sent.
+    pub async fn batch_update<C: Into<CourseId>>(
+        &self,
+        account_id: impl Into<AccountId>,
+        course_ids: impl IntoIterator<Item = C>,
+        event: CourseEvent,
+    ) -> CanvasResult<Progress> {
+        #[derive(Serialize)]
+        struct Params {
+            course_ids: Vec<CourseId>,
+            event: CourseEvent,
+        }
+
+        if event == CourseEvent::Claim {
+            return Err(CanvasError::Encoding(
+                "courses cannot be claimed in a batch update".to_string(),
+            ));
+        }
+
+        let account_id = account_id.into().to_path_segment();
+        let params = Params {
+            course_ids: course_ids.into_iter().map(Into::into).collect(),
+            event,
+        };
+        self.canvas
+            .put_endpoint(
+                &format!("accounts/{account_id}/courses"),
+                RequestBody::form(&params)?,
+            )
+            .await
+    }
+}
+
+pub type ListCourses<M = Async> = ListRequest<Course, ListCoursesParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListCoursesParams {
+    enrollment_type: Option<CourseEnrollmentType>,
+    enrollment_role_id: Option<u64>,
+    enrollment_state: Option<CourseEnrollmentState>,
+    exclude_blueprint_courses: Option<bool>,
+    include: Vec<CourseInclude>,
+    state: Vec<WorkflowState>,
+}
+
+impl<M> ListCourses<M> {
+    /// Only return courses where the current user is enrolled as this type.
+    pub fn enrollment_type(mut self, enrollment_type: CourseEnrollmentType) -> Self {
+        self.params.enrollment_type = Some(enrollment_type);
+        self
+    }
+
+    /// Only return courses where the current user is enrolled with this role.
+    pub fn enrollment_role_id(mut self, role_id: u64) -> Self {
+        self.params.enrollment_role_id = Some(role_id);
+        self
+    }
+
+    /// Only return courses where the current user's enrollment is in this
+    /// state.
+    pub fn enrollment_state(mut self, state: CourseEnrollmentState) -> Self {
+        self.params.enrollment_state = Some(state);
+        self
+    }
+
+    /// Leave out Blueprint courses.
+    pub fn exclude_blueprint_courses(mut self, exclude: bool) -> Self {
+        self.params.exclude_blueprint_courses = Some(exclude);
+        self
+    }
+
+    /// Additional information to include with each course.
+    pub fn include(mut self, include: impl IntoIterator<Item = CourseInclude>) -> Self {
+        self.params.include.extend(include);
+        self
+    }
+
+    /// Only return courses in these states.
+    pub fn state(mut self, state: impl IntoIterator<Item = WorkflowState>) -> Self {
+        self.params.state.extend(state);
+        self
+    }
+}
+
+pub type ListUserCourses<M = Async> = ListRequest<Course, ListUserCoursesParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListUserCoursesParams {
+    enrollment_state: Option<CourseEnrollmentState>,
+    homeroom: Option<bool>,
+    account_id: Option<AccountId>,
+    include: Vec<CourseInclude>,
+    state: Vec<WorkflowState>,
+}
+
+impl<M> ListUserCourses<M> {
+    /// Only return courses where the user's enrollment is in this state.
+    pub fn enrollment_state(mut self, state: CourseEnrollmentState) -> Self {
+        self.params.enrollment_state = Some(state);
+        self
+    }
+
+    /// Only return homeroom courses.
+    pub fn homeroom(mut self, homeroom: bool) -> Self {
+        self.params.homeroom = Some(homeroom);
+        self
+    }
+
+    /// Only return courses in this account or its sub-accounts.
+    pub fn account_id(mut self, account_id: impl Into<AccountId>) -> Self {
+        self.params.account_id = Some(account_id.into());
+        self
+    }
+
+    /// Additional information to include with each course.
+    pub fn include(mut self, include: impl IntoIterator<Item = CourseInclude>) -> Self {
+        self.params.include.extend(include);
+        self
+    }
+
+    /// Only return courses in these states.
+    pub fn state(mut self, state: impl IntoIterator<Item = WorkflowState>) -> Self {
+        self.params.state.extend(state);
+        self
+    }
+}
+
+/// A change to the state of a course.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseEvent {
+    /// Publish the course.
+    Offer,
+    /// Unpublish the course.
+    Claim,
+    Conclude,
+    Delete,
+    /// Restore a deleted course, unpublished.
+    Undelete,
+}
+
+impl CourseEvent {
+    /// The state of a course after the event.
+    pub fn resulting_state(self) -> WorkflowState {
+        match self {
+            CourseEvent::Offer => WorkflowState::Available,
+            CourseEvent::Claim | CourseEvent::Undelete => WorkflowState::Unpublished,
+            CourseEvent::Conclude => WorkflowState::Completed,
+            CourseEvent::Delete => WorkflowState::Deleted,
+        }
+    }
+}
+
+/// A course to create, see [`CourseHandler::create`].
+#[derive(Debug, Clone, Serialize)]
+pub struct NewCourse {
+    course: CourseSettings,
+    offer: Option<bool>,
+    enroll_me: Option<bool>,
+}
+
+impl NewCourse {
+    /// A course with these settings. Canvas fills in the rest from the
+    /// account, and names the course `Unnamed Course` if no name is set.
+    pub fn new(settings: CourseSettings) -> Self {
+        Self {
+            course: settings,
+            offer: None,
+            enroll_me: None,
+        }
+    }
+
+    /// Publish the course right away.
+    pub fn offer(mut self, offer: bool) -> Self {
+        self.offer = Some(offer);
+        self
+    }
+
+    /// Enroll the current user as a teacher of the course.
+    pub fn enroll_me(mut self, enroll_me: bool) -> Self {
+        self.enroll_me = Some(enroll_me);
+        self
+    }
+}
+
+/// The settings of a course to create or update.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CourseSettings {
+    name: Option<String>,
+    course_code: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    start_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    end_at: Option<OffsetDateTime>,
+    restrict_enrollments_to_course_dates: Option<bool>,
+    term_id: Option<TermId>,
+    sis_course_id: Option<String>,
+    integration_id: Option<String>,
+    time_zone: Option<String>,
+    default_view: Option<PageType>,
+    course_format: Option<CourseFormat>,
+    license: Option<String>,
+    is_public: Option<bool>,
+    is_public_to_auth_users: Option<bool>,
+    public_syllabus: Option<bool>,
+    public_syllabus_to_auth: Option<bool>,
+    public_description: Option<String>,
+    syllabus_body: Option<String>,
+    hide_final_grades: Option<bool>,
+    apply_assignment_group_weights: Option<bool>,
+    allow_student_forum_attachments: Option<bool>,
+    allow_wiki_comments: Option<bool>,
+    open_enrollment: Option<bool>,
+    self_enrollment: Option<bool>,
+    grading_standard_id: Option<u64>,
+}
+
+impl CourseSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn course_code(mut self, course_code: impl Into<String>) -> Self {
+        self.course_code = Some(course_code.into());
+        self
+    }
+
+    pub fn start_at(mut self, start_at: OffsetDateTime) -> Self {
+        self.start_at = Some(start_at);
+        self
+    }
+
+    pub fn end_at(mut self, end_at: OffsetDateTime) -> Self {
+        self.end_at = Some(end_at);
+        self
+    }
+
+    /// Only let students participate between the start and end dates of the
+    /// course, instead of those of its term.
+    pub fn restrict_enrollments_to_course_dates(mut self, restrict: bool) -> Self {
+        self.restrict_enrollments_to_course_dates = Some(restrict);
+        self
+    }
+
+    /// The enrollment term of the course.
+    pub fn term_id(mut self, term_id: impl Into<TermId>) -> Self {
+        self.term_id = Some(term_id.into());
+        self
+    }
+
+    /// Requires the permission to manage SIS data.
+    pub fn sis_course_id(mut self, sis_course_id: impl Into<String>) -> Self {
+        self.sis_course_id = Some(sis_course_id.into());
+        self
+    }
+
+    /// Requires the permission to manage SIS data.
+    pub fn integration_id(mut self, integration_id: impl Into<String>) -> Self {
+        self.integration_id = Some(integration_id.into());
+        self
+    }
+
+    /// The IANA time zone of the course, e.g. `Europe/Berlin`.
+    pub fn time_zone(mut self, time_zone: impl Into<String>) -> Self {
+        self.time_zone = Some(time_zone.into());
+        self
+    }
+
+    /// The page users see when they first visit the course.
+    pub fn default_view(mut self, default_view: PageType) -> Self {
+        self.default_view = Some(default_view);
+        self
+    }
+
+    pub fn course_format(mut self, course_format: CourseFormat) -> Self {
+        self.course_format = Some(course_format);
+        self
+    }
+
+    /// The license of the course content, e.g. `cc_by_sa` or `private`.
+    pub fn license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Make the course visible to anyone.
+    pub fn is_public(mut self, is_public: bool) -> Self {
+        self.is_public = Some(is_public);
+        self
+    }
+
+    /// Make the course visible to any logged in user.
+    pub fn is_public_to_auth_users(mut self, is_public: bool) -> Self {
+        self.is_public_to_auth_users = Some(is_public);
+        self
+    }
+
+    /// Make the syllabus visible to anyone.
+    pub fn public_syllabus(mut self, public: bool) -> Self {
+        self.public_syllabus = Some(public);
+        self
+    }
+
+    /// Make the syllabus visible to any logged in user.
+    pub fn public_syllabus_to_auth(mut self, public: bool) -> Self {
+        self.public_syllabus_to_auth = Some(public);
+        self
+    }
+
+    /// The description shown in the public course index.
+    pub fn public_description(mut self, description: impl Into<String>) -> Self {
+        self.public_description = Some(description.into());
+        self
+    }
+
+    pub fn syllabus_body(mut self, syllabus_body: impl Into<String>) -> Self {
+        self.syllabus_body = Some(syllabus_body.into());
+        self
+    }
+
+    /// Hide the totals in the grade summary of students.
+    pub fn hide_final_grades(mut self, hide: bool) -> Self {
+        self.hide_final_grades = Some(hide);
+        self
+    }
+
+    /// Weight final grades by the weights of the assignment groups.
+    pub fn apply_assignment_group_weights(mut self, apply: bool) -> Self {
+        self.apply_assignment_group_weights = Some(apply);
+        self
+    }
+
+    pub fn allow_student_forum_attachments(mut self, allow: bool) -> Self {
+        self.allow_student_forum_attachments = Some(allow);
+        self
+    }
+
+    pub fn allow_wiki_comments(mut self, allow: bool) -> Self {
+        self.allow_wiki_comments = Some(allow);
+        self
+    }
+
+    /// Let anyone with the join code enroll themselves. Requires
+    /// [`self_enrollment`](Self::self_enrollment).
+    pub fn open_enrollment(mut self, open: bool) -> Self {
+        self.open_enrollment = Some(open);
+        self
+    }
+
+    pub fn self_enrollment(mut self, self_enrollment: bool) -> Self {
+        self.self_enrollment = Some(self_enrollment);
+        self
+    }
+
+    pub fn grading_standard_id(mut self, grading_standard_id: u64) -> Self {
+        self.grading_standard_id = Some(grading_standard_id);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{response, serve};
+
+    #[tokio::test]
+    async fn batch_updates_reject_claims_before_sending() {
+        let (base_url, requests) = serve(|_, _| response("200 OK", "", "{}"));
+        let canvas = Canvas::builder()
+            .base_url(&base_url)
+            .allow_http(true)
+            .token("token")
+            .build()
+            .unwrap();
+
+        let result = canvas
+            .courses()
+            .batch_update(1, [1, 2], CourseEvent::Claim)
+            .await;
+        assert!(
+            matches!(result, Err(CanvasError::Encoding(_))),
+            "{result:?}"
+        );
+        assert!(requests.lock().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,1140 @@
+//! Files, and moving them in and out of Canvas.
+//!
+//! Files belong to a course, group or user, see [`FileContext`], and are
+//! managed through [`FileHandler`]; their folders through
+//! [`FolderHandler`](crate::api::folders::FolderHandler).
+//!
+//! Uploads go through Canvas' three step protocol:
+//!
+//! 1. Tell Canvas about the file, which answers with where to upload it.
+//! 2. Upload the file there, usually to a storage service outside Canvas.
+//! 3. Confirm the upload, which returns the new [`File`].
+//!
+//! Uploads are started from the handler of the context the file belongs to,
+//! e.g. [`CourseHandler::upload_file`](crate::api::courses::CourseHandler::upload_file),
+//! or with [`Canvas::upload_file`] for any other upload endpoint.
+//!
+//! Downloads are started with [`Canvas::download_file`], or
+//! [`Canvas::download`] for any URL Canvas hands out, e.g. for submission
+//! attachments. They are streamed to their destination as they arrive.
+use super::request::{Async, ListRequest};
+use crate::{
+    check_status,
+    encoding::RequestBody,
+    error::CanvasError,
+    ids::{CourseId, GroupId, UserId},
+    models::files::{File, FileQuota},
+    params::{FileSort, Order},
+    Canvas, CanvasResult,
+};
+
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use percent_encoding::percent_decode_str;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+        LOCATION, RANGE,
+    },
+    Method, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use url::Url;
+
+/// The course, group or user files and folders belong to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileContext {
+    Course(CourseId),
+    Group(GroupId),
+    User(UserId),
+}
+
+impl FileContext {
+    pub fn course(course_id: impl Into<CourseId>) -> Self {
+        Self::Course(course_id.into())
+    }
+
+    pub fn group(group_id: impl Into<GroupId>) -> Self {
+        Self::Group(group_id.into())
+    }
+
+    pub fn user(user_id: impl Into<UserId>) -> Self {
+        Self::User(user_id.into())
+    }
+
+    /// The endpoint of the context, e.g. `courses/1`.
+    pub(crate) fn endpoint(&self) -> String {
+        match self {
+            Self::Course(id) => format!("courses/{}", id.to_path_segment()),
+            Self::Group(id) => format!("groups/{}", id.to_path_segment()),
+            Self::User(id) => format!("users/{}", id.to_path_segment()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FileHandler {
+    canvas: Canvas,
+}
+impl FileHandler {
+    pub fn new(canvas: Canvas) -> Self {
+        Self { canvas }
+    }
+}
+
+impl FileHandler {
+    /// Get a specific file.
+    pub async fn get(&self, file_id: u64) -> CanvasResult<File> {
+        self.canvas
+            .get_endpoint(&format!("files/{file_id}"), None)
+            .await
+    }
+
+    /// List the files of a course, group or user, from all of its folders.
+    pub fn list(&self, context: &FileContext) -> ListFiles {
+        ListRequest::new(self.canvas.clone(), format!("{}/files", context.endpoint()))
+    }
+
+    /// List the files directly inside a folder.
+    pub fn list_in_folder(&self, folder_id: u64) -> ListFiles {
+        ListRequest::new(self.canvas.clone(), format!("folders/{folder_id}/files"))
+    }
+
+    /// How much storage a course, group or user has, and how much of it is
+    /// used.
+    ///
+    /// Unlike [`Course::storage_quota_used_mb`](crate::models::courses::Course::storage_quota_used_mb),
+    /// which is only included on request, this is in bytes.
+    pub async fn quota(&self, context: &FileContext) -> CanvasResult<FileQuota> {
+        self.canvas
+            .get_endpoint(&format!("{}/files/quota", context.endpoint()), None)
+            .await
+    }
+
+    /// Upload a file to a course, group or user.
+    pub fn upload(&self, context: &FileContext, source: UploadSource) -> FileUpload {
+        self.canvas
+            .upload_file(format!("{}/files", context.endpoint()), source)
+    }
+
+    /// Rename, move, lock or hide a file.
+    pub async fn update(&self, file_id: u64, update: FileUpdate) -> CanvasResult<File> {
+        self.canvas
+            .put_endpoint(&format!("files/{file_id}"), RequestBody::form(&update)?)
+            .await
+    }
+
+    /// Copy a file into a folder, possibly of another context.
+    ///
+    /// Without `on_duplicate`, copying fails if the folder already holds a
+    /// file of the same name.
+    pub async fn copy(
+        &self,
+        file_id: u64,
+        folder_id: u64,
+        on_duplicate: Option<OnDuplicate>,
+    ) -> CanvasResult<File> {
+        #[derive(Serialize)]
+        struct Params {
+            source_file_id: u64,
+            on_duplicate: Option<OnDuplicate>,
+        }
+
+        let params = Params {
+            source_file_id: file_id,
+            on_duplicate,
+        };
+        self.canvas
+            .post_endpoint(
+                &format!("folders/{folder_id}/copy_file"),
+                RequestBody::form(&params)?,
+            )
+            .await
+    }
+
+    /// Delete a file, returning it as it was.
+    pub async fn delete(&self, file_id: u64) -> CanvasResult<File> {
+        self.canvas
+            .delete_endpoint(&format!("files/{file_id}"), RequestBody::Empty)
+            .await
+    }
+}
+
+pub type ListFiles<M = Async> = ListRequest<File, ListFilesParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListFilesParams {
+    content_types: Vec<String>,
+    exclude_content_types: Vec<String>,
+    search_term: Option<String>,
+    sort: Option<FileSort>,
+    order: Option<Order>,
+}
+
+impl<M> ListFiles<M> {
+    /// Only return files of these MIME types, or of these major types, e.g.
+    /// `image`.
+    pub fn content_types(
+        mut self,
+        content_types: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.params
+            .content_types
+            .extend(content_types.into_iter().map(Into::into));
+        self
+    }
+
+    /// Leave out files of these MIME types, or of these major types.
+    pub fn exclude_content_types(
+        mut self,
+        content_types: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.params
+            .exclude_content_types
+            .extend(content_types.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only return files whose name contains this term. Canvas requires at
+    /// least two characters.
+    pub fn search_term(mut self, term: impl Into<String>) -> Self {
+        self.params.search_term = Some(term.into());
+        self
+    }
+
+    /// The column to sort the files by.
+    pub fn sort(mut self, sort: FileSort) -> Self {
+        self.params.sort = Some(sort);
+        self
+    }
+
+    pub fn order(mut self, order: Order) -> Self {
+        self.params.order = Some(order);
+        self
+    }
+}
+
+/// Changes to a file, see [`FileHandler::update`]. Fields left unset stay as
+/// they are.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileUpdate {
+    name: Option<String>,
+    parent_folder_id: Option<u64>,
+    on_duplicate: Option<OnDuplicate>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    lock_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    unlock_at: Option<OffsetDateTime>,
+    locked: Option<bool>,
+    hidden: Option<bool>,
+}
+
+impl FileUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename the file.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Move the file into another folder of the same context.
+    pub fn parent_folder_id(mut self, folder_id: u64) -> Self {
+        self.parent_folder_id = Some(folder_id);
+        self
+    }
+
+    /// What to do if the new name is taken in the target folder.
+    pub fn on_duplicate(mut self, on_duplicate: OnDuplicate) -> Self {
+        self.on_duplicate = Some(on_duplicate);
+        self
+    }
+
+    /// Lock the file from this date on.
+    pub fn lock_at(mut self, lock_at: OffsetDateTime) -> Self {
+        self.lock_at = Some(lock_at);
+        self
+    }
+
+    /// Lock the file until this date.
+    pub fn unlock_at(mut self, unlock_at: OffsetDateTime) -> Self {
+        self.unlock_at = Some(unlock_at);
+        self
+    }
+
+    /// Lock or unlock the file for students.
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.locked = Some(locked);
+        self
+    }
+
+    /// Hide the file from students' file listings, while keeping it
+    /// available through links.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = Some(hidden);
+        self
+    }
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// The contents of a file to upload.
+pub struct UploadSource {
+    name: String,
+    size: Option<u64>,
+    contents: Contents,
+}
+
+enum Contents {
+    Path(PathBuf),
+    Bytes(Bytes),
+    Reader(Pin<Box<dyn AsyncRead + Send>>),
+}
+
+impl UploadSource {
+    /// Upload a local file, named like it unless [`FileUpload::name`] is set.
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            name,
+            size: None,
+            contents: Contents::Path(path),
+        }
+    }
+
+    /// Upload a buffer as a file called `name`.
+    pub fn bytes(name: impl Into<String>, bytes: impl Into<Bytes>) -> Self {
+        let bytes = bytes.into();
+        Self {
+            name: name.into(),
+            size: Some(bytes.len() as u64),
+            contents: Contents::Bytes(bytes),
+        }
+    }
+
+    /// Upload `size` bytes read from `reader` as a file called `name`.
+    ///
+    /// The size has to be known up front, as storage services reject uploads
+    /// without a `Content-Length`.
+    pub fn reader(
+        name: impl Into<String>,
+        reader: impl AsyncRead + Send + 'static,
+        size: u64,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            size: Some(size),
+            contents: Contents::Reader(Box::pin(reader)),
+        }
+    }
+
+    async fn open(self) -> CanvasResult<(u64, ByteStream)> {
+        match self.contents {
+            Contents::Path(path) => {
+                let file = tokio::fs::File::open(&path).await?;
+                let size = file.metadata().await?.len();
+                Ok((size, Box::pin(ReaderStream::new(file))))
+            }
+            Contents::Bytes(bytes) => Ok((
+                bytes.len() as u64,
+                Box::pin(stream::once(async { Ok(bytes) })),
+            )),
+            Contents::Reader(reader) => Ok((
+                self.size.unwrap_or_default(),
+                Box::pin(ReaderStream::new(reader)),
+            )),
+        }
+    }
+}
+
+/// How far an upload has got, as passed to [`FileUpload::on_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Bytes of the file sent so far.
+    pub sent: u64,
+    /// Size of the file in bytes.
+    pub total: u64,
+}
+
+/// What to do if a file with the same name already exists in the folder.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDuplicate {
+    /// Replace the existing file. The default.
+    Overwrite,
+    /// Add a number to the name of the new file.
+    Rename,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct PreflightParams {
+    name: String,
+    size: Option<u64>,
+    content_type: Option<String>,
+    parent_folder_id: Option<u64>,
+    parent_folder_path: Option<String>,
+    on_duplicate: Option<OnDuplicate>,
+}
+
+#[derive(Deserialize)]
+struct Preflight {
+    upload_url: String,
+    #[serde(default)]
+    upload_params: serde_json::Map<String, Value>,
+    file_param: Option<String>,
+}
+
+type ProgressCallback = Arc<dyn Fn(UploadProgress) + Send + Sync>;
+
+/// A file upload, started with `upload_file` on a handler.
+pub struct FileUpload {
+    canvas: Canvas,
+    endpoint: String,
+    source: UploadSource,
+    params: PreflightParams,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Canvas {
+    /// Upload a file through the upload endpoint `endpoint`, e.g.
+    /// `groups/1/files`.
+    pub fn upload_file(&self, endpoint: impl Into<String>, source: UploadSource) -> FileUpload {
+        FileUpload {
+            canvas: self.clone(),
+            endpoint: endpoint.into(),
+            source,
+            params: Default::default(),
+            on_progress: None,
+        }
+    }
+}
+
+impl FileUpload {
+    /// The name of the file in Canvas, instead of the one of the source.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.source.name = name.into();
+        self
+    }
+
+    /// The MIME type of the file. If not set, Canvas infers it from the name.
+    ///
+    /// Types that are not valid header values, e.g. containing line breaks,
+    /// fail the upload with [`CanvasError::Encoding`].
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.params.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Put the file into the folder with this ID.
+    pub fn parent_folder_id(mut self, folder_id: u64) -> Self {
+        self.params.parent_folder_id = Some(folder_id);
+        self
+    }
+
+    /// Put the file into the folder at this path, e.g. `course
+    /// files/handouts`, creating it if needed.
+    pub fn parent_folder_path(mut self, path: impl Into<String>) -> Self {
+        self.params.parent_folder_path = Some(path.into());
+        self
+    }
+
+    pub fn on_duplicate(mut self, on_duplicate: OnDuplicate) -> Self {
+        self.params.on_duplicate = Some(on_duplicate);
+        self
+    }
+
+    /// Call `callback` as the contents of the file are sent.
+    pub fn on_progress(
+        mut self,
+        callback: impl Fn(UploadProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Run all three steps of the upload.
+    pub async fn send(self) -> CanvasResult<File> {
+        // It goes into the hand-built multipart body as is.
+        if let Some(content_type) = &self.params.content_type {
+            if HeaderValue::from_str(content_type).is_err() {
+                return Err(CanvasError::Encoding(format!(
+                    "invalid content type {content_type:?}"
+                )));
+            }
+        }
+
+        let name = self.source.name.clone();
+        let (size, contents) = self.source.open().await?;
+
+        let params = PreflightParams {
+            name: name.clone(),
+            size: Some(size),
+            ..self.params
+        };
+        let preflight: Preflight = self
+            .canvas
+            .post_endpoint(&self.endpoint, RequestBody::form(&params)?)
+            .await?;
+
+        let resp = upload(
+            &self.canvas,
+            preflight,
+            &name,
+            params.content_type.as_deref(),
+            size,
+            contents,
+            self.on_progress,
+        )
+        .await?;
+        confirm(&self.canvas, resp).await
+    }
+}
+
+/// Step 2: post the file as `multipart/form-data`, after the parameters
+/// Canvas asked for.
+///
+/// The request does not go through the middleware chain: it does not carry
+/// the token (storage services reject it), and its redirect must not be
+/// followed, as it leads to the confirmation.
+async fn upload(
+    canvas: &Canvas,
+    preflight: Preflight,
+    name: &str,
+    content_type: Option<&str>,
+    size: u64,
+    contents: ByteStream,
+    on_progress: Option<ProgressCallback>,
+) -> CanvasResult<reqwest::Response> {
+    let boundary = format!(
+        "canvasapi-{:016x}{:016x}",
+        fastrand::u64(..),
+        fastrand::u64(..)
+    );
+    let mut head = String::new();
+    for (key, value) in &preflight.upload_params {
+        let value = match value {
+            Value::String(value) => value.clone(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        head.push_str(&format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{value}\r\n",
+            escape_quoted(key)
+        ));
+    }
+    // The file has to come last, anything after it is ignored.
+    head.push_str(&format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+        escape_quoted(preflight.file_param.as_deref().unwrap_or("file")),
+        escape_quoted(name),
+        content_type.unwrap_or("application/octet-stream"),
+    ));
+    let tail = format!("\r\n--{boundary}--\r\n");
+    let length = head.len() as u64 + size + tail.len() as u64;
+
+    let mut sent = 0;
+    let contents = contents.inspect(move |chunk| {
+        if let (Ok(chunk), Some(on_progress)) = (chunk, &on_progress) {
+            sent += chunk.len() as u64;
+            on_progress(UploadProgress { sent, total: size });
+        }
+    });
+    let body = stream::once(async { Ok(Bytes::from(head)) })
+        .chain(contents)
+        .chain(stream::once(async { Ok(Bytes::from(tail)) }));
+
+    let resp = canvas
+        .http()
+        .post(&preflight.upload_url)
+        .header(
+            CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .header(CONTENT_LENGTH, length)
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await?;
+    if resp.status().is_redirection() {
+        return Ok(resp);
+    }
+    check_status(Method::POST, resp).await
+}
+
+/// Step 3: follow the response of the upload to the new file.
+async fn confirm(canvas: &Canvas, resp: reqwest::Response) -> CanvasResult<File> {
+    let location = resp
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .and_then(|location| resp.url().join(location).ok());
+
+    match (resp.status(), location) {
+        // The storage service redirects to Canvas, which only marks the file
+        // as available once the redirect is followed.
+        (status, Some(location)) if status.is_redirection() => {
+            canvas.get_url(location.as_str(), None).await
+        }
+        // Some storage services answer with the file directly, others only
+        // point at it.
+        (StatusCode::CREATED | StatusCode::OK, location) => {
+            let body = resp.bytes().await?;
+            match (serde_json::from_slice::<File>(&body), location) {
+                (Ok(file), _) => Ok(file),
+                (Err(_), Some(location)) => canvas.get_url(location.as_str(), None).await,
+                (Err(e), None) => Err(e.into()),
+            }
+        }
+        // Error statuses were turned into errors with the upload already, so
+        // this is a success or redirect that leads nowhere.
+        _ => Err(CanvasError::from_response(Method::POST, resp).await),
+    }
+}
+
+// Quotes and line breaks would end the header parameter early.
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// A file download, started with [`Canvas::download_file`] or
+/// [`Canvas::download`].
+///
+/// Canvas answers download URLs with a redirect to where the file is stored,
+/// which is followed without the token once it leaves the instance. The body
+/// is written out as it arrives, and its size checked against the expected
+/// one at the end.
+pub struct Download {
+    canvas: Canvas,
+    url: String,
+    name: Option<String>,
+    size: Option<u64>,
+    offset: u64,
+    resume: bool,
+}
+
+impl Canvas {
+    /// Download `file`, saving it under its display name with
+    /// [`Download::to_dir`].
+    pub fn download_file(&self, file: &File) -> Download {
+        // Files locked for the current user come without a URL, but may
+        // still be accessible to e.g. teachers through the web route.
+        let url = file.url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/files/{}/download?download_frd=1",
+                self.base_url(),
+                file.id
+            )
+        });
+        Download {
+            name: Some(file.display_name.clone()),
+            size: Some(file.size),
+            ..self.download(url)
+        }
+    }
+
+    /// Download the file at `url`, e.g. the `url` of an attachment.
+    ///
+    /// Unlike with [`Canvas::get`], the URL may point outside the instance,
+    /// as Canvas hands out download URLs of other hosts. The token is still
+    /// only sent to the instance itself.
+    pub fn download(&self, url: impl Into<String>) -> Download {
+        Download {
+            canvas: self.clone(),
+            url: url.into(),
+            name: None,
+            size: None,
+            offset: 0,
+            resume: false,
+        }
+    }
+}
+
+impl Download {
+    /// The name to save the file under with [`Download::to_dir`], instead of
+    /// the one the server suggests.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// The size of the file in bytes. If not set, the size announced by the
+    /// server is checked instead.
+    pub fn size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Skip the first `offset` bytes of the file, as the writer passed to
+    /// [`Download::to_writer`] already holds them.
+    pub fn resume_from(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Continue the partial file left behind by an earlier download with
+    /// [`Download::to_path`] or [`Download::to_dir`], instead of replacing it.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Stream the file into `writer`, returning its size.
+    pub async fn to_writer<W>(self, writer: &mut W) -> CanvasResult<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        self.start(self.offset).await?.write(writer).await
+    }
+
+    /// Save the file at `path`, returning its size.
+    pub async fn to_path(self, path: impl AsRef<Path>) -> CanvasResult<u64> {
+        let path = path.as_ref();
+        let mut offset = 0;
+        if self.resume {
+            match tokio::fs::metadata(path).await {
+                Ok(metadata) => offset = metadata.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        if offset > 0 && self.size == Some(offset) {
+            return Ok(offset);
+        }
+
+        let transfer = self.start(offset).await?;
+        if transfer.resp.is_none() {
+            return Ok(offset);
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(path)
+            .await?;
+        transfer.write(&mut file).await
+    }
+
+    /// Save the file into the directory `dir`, returning its path.
+    ///
+    /// The file is named after [`Download::name`], or else the name suggested
+    /// by the server, made safe to use as a file name.
+    pub async fn to_dir(self, dir: impl AsRef<Path>) -> CanvasResult<PathBuf> {
+        let dir = dir.as_ref();
+        if let Some(name) = &self.name {
+            let path = dir.join(sanitize_filename(name));
+            self.to_path(&path).await?;
+            return Ok(path);
+        }
+
+        // Without a name there is no partial file to find, so start over.
+        let transfer = self.start(0).await?;
+        let name = transfer
+            .resp
+            .as_ref()
+            .map(suggested_filename)
+            .unwrap_or_default();
+        let path = dir.join(sanitize_filename(&name));
+        let mut file = tokio::fs::File::create(&path).await?;
+        transfer.write(&mut file).await?;
+        Ok(path)
+    }
+
+    /// Request the file from `offset` on.
+    async fn start(&self, offset: u64) -> CanvasResult<Transfer> {
+        // Masquerading only applies to the instance, other hosts would just
+        // learn the user ID.
+        let url = match Url::parse(&self.url) {
+            Ok(url) if self.canvas.is_same_origin(&url) => self.canvas.masqueraded_url(&self.url),
+            _ => Cow::Borrowed(self.url.as_str()),
+        };
+        let mut req = self.canvas.http().get(url.as_ref());
+        if offset > 0 {
+            req = req.header(RANGE, format!("bytes={offset}-"));
+        }
+
+        let resp = match self.canvas.execute_uncached(req.build()?).await {
+            Ok(resp) => resp,
+            // The range starts at the end of the file, which is complete.
+            Err(e) if offset > 0 && e.status() == Some(StatusCode::RANGE_NOT_SATISFIABLE) => {
+                let total = e
+                    .api_error()
+                    .and_then(|e| e.headers.get(CONTENT_RANGE))
+                    .and_then(|range| range.to_str().ok())
+                    .and_then(content_range_total);
+                return match self.size.or(total) {
+                    Some(expected) if expected == offset => Ok(Transfer {
+                        resp: None,
+                        expected: Some(expected),
+                        offset,
+                        skip: 0,
+                    }),
+                    _ => Err(e),
+                };
+            }
+            Err(e) => return Err(e),
+        };
+
+        let (announced, skip) = resume_point(resp.status(), resp.headers(), offset);
+        Ok(Transfer {
+            resp: Some(resp),
+            expected: self.size.or(announced),
+            offset,
+            skip,
+        })
+    }
+}
+
+/// The size of the whole file as announced by a response to a request for it
+/// from `offset` on, and how many bytes at the start of its body the
+/// destination already holds.
+fn resume_point(status: StatusCode, headers: &HeaderMap, offset: u64) -> (Option<u64>, u64) {
+    if status == StatusCode::PARTIAL_CONTENT {
+        let total = headers
+            .get(CONTENT_RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(content_range_total);
+        (total, 0)
+    } else {
+        // Servers ignoring the range send the whole file again.
+        let length = headers
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse().ok());
+        (length, offset)
+    }
+}
+
+/// A download whose response has arrived.
+struct Transfer {
+    // `None` if the destination already holds the whole file.
+    resp: Option<reqwest::Response>,
+    expected: Option<u64>,
+    // Bytes the destination already holds.
+    offset: u64,
+    // Bytes at the start of the body the destination already holds.
+    skip: u64,
+}
+
+impl Transfer {
+    async fn write<W>(self, writer: &mut W) -> CanvasResult<u64>
+    where
+        W: AsyncWrite + Unpin + ?Sized,
+    {
+        let Some(resp) = self.resp else {
+            return Ok(self.offset);
+        };
+        let mut skip = self.skip;
+        let mut received = self.offset;
+        let mut body = resp.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            let mut chunk = chunk?;
+            if skip > 0 {
+                let skipped = skip.min(chunk.len() as u64);
+                chunk = chunk.slice(skipped as usize..);
+                skip -= skipped;
+            }
+            writer.write_all(&chunk).await?;
+            received += chunk.len() as u64;
+        }
+        writer.flush().await?;
+
+        match self.expected {
+            Some(expected) if expected != received => {
+                Err(CanvasError::DownloadSize { expected, received })
+            }
+            _ => Ok(received),
+        }
+    }
+}
+
+/// The total size in a `Content-Range` header, e.g. `bytes 100-199/200`.
+fn content_range_total(range: &str) -> Option<u64> {
+    range.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+/// The file name from the `Content-Disposition` header, or else the last
+/// segment of the URL the file was served from.
+fn suggested_filename(resp: &reqwest::Response) -> String {
+    let disposition = resp
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let param = |name: &str| {
+        disposition.split(';').find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            (key.trim().eq_ignore_ascii_case(name)).then(|| value.trim())
+        })
+    };
+
+    // `filename*=UTF-8''...` takes precedence over the plain `filename`.
+    if let Some((_, encoded)) = param("filename*").and_then(|value| value.split_once("''")) {
+        return percent_decode_str(encoded).decode_utf8_lossy().into_owned();
+    }
+    if let Some(name) = param("filename") {
+        return name.trim_matches('"').to_string();
+    }
+    resp.url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// Names Windows reserves for devices, whatever the extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turn a name chosen by whoever uploaded the file into one that stays inside
+/// the directory it is saved to, and is valid on common file systems.
+fn sanitize_filename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // This also turns `.` and `..` into nothing, and avoids hidden files.
+    let trimmed = replaced.trim_matches(|c: char| c == '.' || c.is_whitespace());
+
+    let stem = trimmed.split('.').next().unwrap_or_default();
+    let mut name = if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        format!("_{trimmed}")
+    } else {
+        trimmed.to_string()
+    };
+    if name.is_empty() {
+        name = "download".to_string();
+    }
+
+    // Most file systems allow 255 bytes, keep the extension when shortening.
+    const MAX_LEN: usize = 255;
+    if name.len() > MAX_LEN {
+        let extension = match name.rfind('.') {
+            Some(dot) if name.len() - dot <= 16 => name[dot..].to_string(),
+            _ => String::new(),
+        };
+        let mut end = MAX_LEN - extension.len();
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+        name.push_str(&extension);
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_names_leaving_the_directory() {
+        assert_eq!(sanitize_filename(".."), "download");
+        assert_eq!(sanitize_filename("."), "download");
+        assert_eq!(sanitize_filename("a/../../b"), "a_.._.._b");
+        assert_eq!(sanitize_filename("..\\evil.exe"), "_evil.exe");
+        assert_eq!(sanitize_filename(" .hidden. "), "hidden");
+    }
+
+    #[test]
+    fn sanitizes_reserved_names_and_control_characters() {
+        assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_filename("lpt1"), "_lpt1");
+        assert_eq!(sanitize_filename("CONTACTS.txt"), "CONTACTS.txt");
+        assert_eq!(sanitize_filename("a\nb\tc\u{7f}.txt"), "a_b_c_.txt");
+        assert_eq!(sanitize_filename("what?<now>.pdf"), "what__now_.pdf");
+    }
+
+    #[test]
+    fn truncates_long_names_on_char_boundaries() {
+        // 3 bytes each, so 255 bytes do not end on a boundary after the
+        // extension.
+        let name = format!("{}.pdf", "€".repeat(100));
+        let sanitized = sanitize_filename(&name);
+        assert!(sanitized.len() <= 255);
+        assert_eq!(sanitized, format!("{}.pdf", "€".repeat(83)));
+
+        // Overlong extensions are not kept.
+        let name = format!("{}.{}", "a".repeat(300), "b".repeat(20));
+        assert_eq!(sanitize_filename(&name), "a".repeat(255));
+    }
+
+    #[test]
+    fn parses_content_range_totals() {
+        assert_eq!(content_range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(content_range_total("bytes */200"), Some(200));
+        assert_eq!(content_range_total("bytes 100-199/*"), None);
+        assert_eq!(content_range_total("bytes"), None);
+    }
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.clone(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn resumes_partial_content() {
+        let partial = headers(&[
+            (CONTENT_RANGE, "bytes 100-199/200"),
+            (CONTENT_LENGTH, "100"),
+        ]);
+        assert_eq!(
+            resume_point(StatusCode::PARTIAL_CONTENT, &partial, 100),
+            (Some(200), 0)
+        );
+        let unknown = headers(&[(CONTENT_RANGE, "bytes 100-199/*")]);
+        assert_eq!(
+            resume_point(StatusCode::PARTIAL_CONTENT, &unknown, 100),
+            (None, 0)
+        );
+    }
+
+    #[test]
+    fn skips_what_is_held_when_the_range_is_ignored() {
+        let whole = headers(&[(CONTENT_LENGTH, "200")]);
+        assert_eq!(resume_point(StatusCode::OK, &whole, 100), (Some(200), 100));
+        assert_eq!(resume_point(StatusCode::OK, &whole, 0), (Some(200), 0));
+    }
+
+    async fn transfer(status: StatusCode, headers: HeaderMap, body: &'static str) -> Vec<u8> {
+        let mut resp = http::Response::new(body);
+        *resp.status_mut() = status;
+        *resp.headers_mut() = headers;
+        let resp = reqwest::Response::from(resp);
+        let (expected, skip) = resume_point(resp.status(), resp.headers(), 4);
+
+        let mut written = Vec::new();
+        let received = Transfer {
+            resp: Some(resp),
+            expected,
+            offset: 4,
+            skip,
+        }
+        .write(&mut written)
+        .await
+        .unwrap();
+        assert_eq!(received, 10);
+        written
+    }
+
+    #[tokio::test]
+    async fn writes_the_rest_of_the_file() {
+        let partial = headers(&[(CONTENT_RANGE, "bytes 4-9/10"), (CONTENT_LENGTH, "6")]);
+        assert_eq!(
+            transfer(StatusCode::PARTIAL_CONTENT, partial, "456789").await,
+            b"456789"
+        );
+        let whole = headers(&[(CONTENT_LENGTH, "10")]);
+        assert_eq!(
+            transfer(StatusCode::OK, whole, "0123456789").await,
+            b"456789"
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_short_downloads() {
+        let resp = reqwest::Response::from(http::Response::new("45"));
+        let result = Transfer {
+            resp: Some(resp),
+            expected: Some(10),
+            offset: 4,
+            skip: 0,
+        }
+        .write(&mut Vec::new())
+        .await;
+        assert!(matches!(
+            result,
+            Err(CanvasError::DownloadSize {
+                expected: 10,
+                received: 6
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_content_types_that_would_break_the_body() {
+        let canvas = Canvas::builder()
+            .base_url("https://canvas.test")
+            .token("token")
+            .build()
+            .unwrap();
+        let result = canvas
+            .upload_file("users/self/files", UploadSource::bytes("a.txt", "a"))
+            .content_type("text/plain\r\n\r\n--injected")
+            .send()
+            .await;
+        assert!(matches!(result, Err(CanvasError::Encoding(_))));
+    }
+
+    #[tokio::test]
+    async fn confirmation_is_fetched_again_when_cut_off() {
+        use crate::tests::{response, serve};
+        use reqwest::ResponseBuilderExt;
+        use std::sync::Mutex;
+
+        let count = Mutex::new(0);
+        let (base_url, requests) = serve(move |_, _| {
+            let mut count = count.lock().unwrap();
+            *count += 1;
+            if *count == 1 {
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 100\r\nconnection: close\r\n\r\n{\"id\"".to_string()
+            } else {
+                response(
+                    "200 OK",
+                    "",
+                    r#"{"id":1,"display_name":"a.txt","filename":"a.txt","size":1,"locked":false,"hidden":false}"#,
+                )
+            }
+        });
+        let canvas = Canvas::builder()
+            .base_url(&base_url)
+            .allow_http(true)
+            .token("token")
+            .retry_policy(crate::retry::RetryPolicy {
+                initial_backoff: std::time::Duration::ZERO,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let redirect = http::Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .url(Url::parse("https://storage.test/upload").unwrap())
+            .header(
+                LOCATION,
+                format!("{base_url}/api/v1/files/1/create_success"),
+            )
+            .body("")
+            .unwrap();
+        let file = confirm(&canvas, redirect.into()).await.unwrap();
+        assert_eq!(file.id, 1);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+}
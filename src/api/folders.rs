@@ -0,0 +1,329 @@
+use super::{
+    files::{FileContext, FileHandler},
+    request::{Async, ListRequest},
+};
+use crate::{
+    encoding::RequestBody,
+    models::files::{File, Folder},
+    Canvas, CanvasResult, PaginatedVec,
+};
+
+use futures::StreamExt;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+use std::collections::VecDeque;
+use time::OffsetDateTime;
+
+// Folder names may contain anything but `/`, so everything but unreserved
+// characters is escaped in each segment of a path.
+const SEGMENT_ESCAPE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[derive(Clone)]
+pub struct FolderHandler {
+    canvas: Canvas,
+}
+impl FolderHandler {
+    pub fn new(canvas: Canvas) -> Self {
+        Self { canvas }
+    }
+}
+
+impl FolderHandler {
+    /// Get a specific folder.
+    pub async fn get(&self, folder_id: u64) -> CanvasResult<Folder> {
+        self.canvas
+            .get_endpoint(&format!("folders/{folder_id}"), None)
+            .await
+    }
+
+    /// Get the root folder of a course, group or user.
+    pub async fn root(&self, context: &FileContext) -> CanvasResult<Folder> {
+        self.canvas
+            .get_endpoint(&format!("{}/folders/root", context.endpoint()), None)
+            .await
+    }
+
+    /// Get the folders along `path`, e.g. `handouts/week 1`, starting with
+    /// the root folder and ending with the one the path leads to.
+    pub async fn resolve_path(
+        &self,
+        context: &FileContext,
+        path: &str,
+    ) -> CanvasResult<Vec<Folder>> {
+        let segments: Vec<String> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| utf8_percent_encode(segment, SEGMENT_ESCAPE).to_string())
+            .collect();
+        self.canvas
+            .get_endpoint(
+                &format!(
+                    "{}/folders/by_path/{}",
+                    context.endpoint(),
+                    segments.join("/")
+                ),
+                None,
+            )
+            .await
+    }
+
+    /// List the folders directly inside a folder.
+    pub fn list(&self, folder_id: u64) -> ListFolders {
+        ListRequest::new(self.canvas.clone(), format!("folders/{folder_id}/folders"))
+    }
+
+    /// List all folders of a course, group or user, flattened.
+    pub fn list_all(&self, context: &FileContext) -> ListFolders {
+        ListRequest::new(
+            self.canvas.clone(),
+            format!("{}/folders", context.endpoint()),
+        )
+    }
+
+    /// Create a folder in a course, group or user.
+    pub async fn create(&self, context: &FileContext, folder: NewFolder) -> CanvasResult<Folder> {
+        self.canvas
+            .post_endpoint(
+                &format!("{}/folders", context.endpoint()),
+                RequestBody::form(&folder)?,
+            )
+            .await
+    }
+
+    /// Rename, move, lock or hide a folder.
+    pub async fn update(&self, folder_id: u64, update: FolderUpdate) -> CanvasResult<Folder> {
+        self.canvas
+            .put_endpoint(
+                &format!("folders/{folder_id}"),
+                RequestBody::form(&update.0)?,
+            )
+            .await
+    }
+
+    /// Copy a folder and everything in it into another folder, possibly of
+    /// another context.
+    pub async fn copy(&self, folder_id: u64, to_folder_id: u64) -> CanvasResult<Folder> {
+        #[derive(Serialize)]
+        struct Params {
+            source_folder_id: u64,
+        }
+
+        self.canvas
+            .post_endpoint(
+                &format!("folders/{to_folder_id}/copy_folder"),
+                RequestBody::form(&Params {
+                    source_folder_id: folder_id,
+                })?,
+            )
+            .await
+    }
+
+    /// Delete a folder, returning it as it was.
+    ///
+    /// Canvas refuses to delete folders that are not empty, unless `force` is
+    /// set, which deletes their contents as well.
+    pub async fn delete(&self, folder_id: u64, force: bool) -> CanvasResult<Folder> {
+        #[derive(Serialize)]
+        struct Params {
+            force: bool,
+        }
+
+        self.canvas
+            .delete_endpoint(
+                &format!("folders/{folder_id}"),
+                RequestBody::form(&Params { force })?,
+            )
+            .await
+    }
+
+    /// Stream every file under a folder, including those in subfolders at
+    /// any depth.
+    ///
+    /// Each file comes with its path from the root folder of its context, e.g.
+    /// `course files/handouts/week 1.pdf`. The files of a folder are yielded
+    /// before those of its subfolders.
+    pub fn walk(&self, folder_id: u64) -> PaginatedVec<CanvasResult<WalkedFile>> {
+        use async_stream::stream;
+
+        let handler = self.clone();
+        let files = FileHandler::new(self.canvas.clone());
+
+        Box::pin(stream! {
+            let mut queue = match handler.get(folder_id).await {
+                Ok(folder) => VecDeque::from([folder]),
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+
+            while let Some(folder) = queue.pop_front() {
+                if folder.files_count > 0 {
+                    let mut listing = files.list_in_folder(folder.id).send().await;
+                    while let Some(file) = listing.next().await {
+                        yield file.map(|file| WalkedFile {
+                            path: format!("{}/{}", folder.full_name, file.display_name),
+                            file,
+                        });
+                    }
+                }
+
+                if folder.folders_count > 0 {
+                    let mut subfolders = handler.list(folder.id).send().await;
+                    while let Some(subfolder) = subfolders.next().await {
+                        match subfolder {
+                            Ok(subfolder) => queue.push_back(subfolder),
+                            Err(e) => yield Err(e),
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// A file found by [`FolderHandler::walk`].
+#[derive(Debug, Clone)]
+pub struct WalkedFile {
+    /// The path of the file from the root folder of its context.
+    pub path: String,
+    pub file: File,
+}
+
+pub type ListFolders<M = Async> = ListRequest<Folder, ListFoldersParams, M>;
+
+// The endpoint takes no options besides the page size.
+#[derive(Debug, Default, Serialize)]
+pub struct ListFoldersParams {}
+
+/// A folder to create, see [`FolderHandler::create`].
+#[derive(Debug, Clone, Serialize)]
+pub struct NewFolder {
+    parent_folder_path: Option<String>,
+    #[serde(flatten)]
+    settings: FolderSettings,
+}
+
+impl NewFolder {
+    /// A folder called `name`, put into the root folder unless
+    /// [`parent_folder_id`](Self::parent_folder_id) or
+    /// [`parent_folder_path`](Self::parent_folder_path) is set.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            parent_folder_path: None,
+            settings: FolderSettings {
+                name: Some(name.into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Put the folder into the folder with this ID.
+    pub fn parent_folder_id(mut self, folder_id: u64) -> Self {
+        self.settings.parent_folder_id = Some(folder_id);
+        self
+    }
+
+    /// Put the folder into the folder at this path, e.g. `handouts/week 1`,
+    /// creating it if needed.
+    pub fn parent_folder_path(mut self, path: impl Into<String>) -> Self {
+        self.parent_folder_path = Some(path.into());
+        self
+    }
+
+    pub fn lock_at(mut self, lock_at: OffsetDateTime) -> Self {
+        self.settings.lock_at = Some(lock_at);
+        self
+    }
+
+    pub fn unlock_at(mut self, unlock_at: OffsetDateTime) -> Self {
+        self.settings.unlock_at = Some(unlock_at);
+        self
+    }
+
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.settings.locked = Some(locked);
+        self
+    }
+
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.settings.hidden = Some(hidden);
+        self
+    }
+
+    /// The position of the folder among its siblings.
+    pub fn position(mut self, position: u32) -> Self {
+        self.settings.position = Some(position);
+        self
+    }
+}
+
+/// Changes to a folder, see [`FolderHandler::update`]. Fields left unset stay
+/// as they are.
+#[derive(Debug, Clone, Default)]
+pub struct FolderUpdate(FolderSettings);
+
+impl FolderUpdate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename the folder.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.0.name = Some(name.into());
+        self
+    }
+
+    /// Move the folder into another folder of the same context.
+    pub fn parent_folder_id(mut self, folder_id: u64) -> Self {
+        self.0.parent_folder_id = Some(folder_id);
+        self
+    }
+
+    /// Lock the folder from this date on.
+    pub fn lock_at(mut self, lock_at: OffsetDateTime) -> Self {
+        self.0.lock_at = Some(lock_at);
+        self
+    }
+
+    /// Lock the folder until this date.
+    pub fn unlock_at(mut self, unlock_at: OffsetDateTime) -> Self {
+        self.0.unlock_at = Some(unlock_at);
+        self
+    }
+
+    /// Lock or unlock the folder for students.
+    pub fn locked(mut self, locked: bool) -> Self {
+        self.0.locked = Some(locked);
+        self
+    }
+
+    /// Hide the folder from students' file listings.
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.0.hidden = Some(hidden);
+        self
+    }
+
+    /// The position of the folder among its siblings.
+    pub fn position(mut self, position: u32) -> Self {
+        self.0.position = Some(position);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+struct FolderSettings {
+    name: Option<String>,
+    parent_folder_id: Option<u64>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    lock_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    unlock_at: Option<OffsetDateTime>,
+    locked: Option<bool>,
+    hidden: Option<bool>,
+    position: Option<u32>,
+}
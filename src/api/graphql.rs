@@ -0,0 +1,183 @@
+use crate::{error::CanvasError, Canvas, CanvasResult, PaginatedVec, RequestBody};
+
+use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+
+/// Runs queries against the GraphQL API at `/api/graphql`.
+#[derive(Clone)]
+pub struct GraphQLHandler {
+    canvas: Canvas,
+}
+impl GraphQLHandler {
+    pub fn new(canvas: Canvas) -> Self {
+        Self { canvas }
+    }
+}
+
+/// An error reported in the `errors` array of a GraphQL response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQLError {
+    pub message: String,
+    #[serde(default)]
+    pub locations: Vec<GraphQLErrorLocation>,
+    // The path to the field that failed, made of field names and list indices.
+    #[serde(default)]
+    pub path: Vec<Value>,
+    pub extensions: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct GraphQLErrorLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Display for GraphQLError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.path.is_empty() {
+            let path: Vec<_> = self
+                .path
+                .iter()
+                .map(|segment| match segment {
+                    Value::String(field) => field.clone(),
+                    other => other.to_string(),
+                })
+                .collect();
+            write!(f, " (at {})", path.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    query: &'a str,
+    variables: &'a Value,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+impl GraphQLHandler {
+    /// Run `query` with `variables` and decode its `data`.
+    ///
+    /// If the response contains any `errors`, they are returned as
+    /// [`CanvasError::GraphQL`], even if part of the data could be fetched.
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: impl Serialize,
+    ) -> CanvasResult<T> {
+        let variables = serde_json::to_value(variables)?;
+        self.send(query, &variables).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, query: &str, variables: &Value) -> CanvasResult<T> {
+        let body = RequestBody::json(&Request { query, variables })?;
+        let url = format!("{}/api/graphql", self.canvas.base_url());
+        let resp: Response<T> = self.canvas.request_url(Method::POST, &url, body).await?;
+        if !resp.errors.is_empty() {
+            return Err(CanvasError::GraphQL(resp.errors));
+        }
+        resp.data.ok_or(CanvasError::GraphQL(Vec::new()))
+    }
+
+    /// Stream all nodes of a Relay style connection, following its cursors.
+    ///
+    /// `query` has to take the cursor as an `$after: String` variable and
+    /// select `pageInfo { hasNextPage endCursor }` along with either `nodes`
+    /// or `edges { node }` of the connection. `connection` is the path of
+    /// field names leading to it from `data`, e.g.
+    /// `&["course", "assignmentsConnection"]`. Set the page size through
+    /// `variables`, e.g. `first`.
+    pub fn paginate<T: DeserializeOwned + Send + 'static>(
+        &self,
+        query: &str,
+        variables: impl Serialize,
+        connection: &[&str],
+    ) -> PaginatedVec<CanvasResult<T>> {
+        use async_stream::stream;
+
+        let handler = self.clone();
+        let query = query.to_string();
+        let connection: Vec<String> = connection.iter().map(|s| s.to_string()).collect();
+        let variables = serde_json::to_value(variables);
+
+        Box::pin(stream! {
+            let mut variables = match variables {
+                Ok(Value::Object(variables)) => variables,
+                Ok(Value::Null) => Default::default(),
+                Ok(_) => Err(CanvasError::Encoding(
+                    "GraphQL variables must be an object".to_string(),
+                ))?,
+                Err(e) => Err(CanvasError::from(e))?,
+            };
+
+            loop {
+                let data: Value = handler
+                    .send(&query, &Value::Object(variables.clone()))
+                    .await?;
+                let (nodes, page_info) = split_connection(data, &connection)?;
+                for node in nodes {
+                    yield serde_json::from_value::<T>(node).map_err(CanvasError::from);
+                }
+
+                match page_info.end_cursor {
+                    Some(cursor) if page_info.has_next_page => {
+                        variables.insert("after".to_string(), Value::String(cursor));
+                    }
+                    _ => break,
+                }
+            }
+        })
+    }
+}
+
+/// Take the nodes and page info of the connection at `path` out of `data`.
+fn split_connection(mut data: Value, path: &[String]) -> CanvasResult<(Vec<Value>, PageInfo)> {
+    let missing = |missing| CanvasError::GraphQLConnection {
+        path: path.join("."),
+        missing,
+    };
+
+    let mut connection = &mut data;
+    for field in path {
+        connection = connection
+            .get_mut(field)
+            .filter(|value| !value.is_null())
+            .ok_or_else(|| missing("connection"))?;
+    }
+
+    let page_info = serde_json::from_value(
+        connection
+            .get_mut("pageInfo")
+            .map(Value::take)
+            .ok_or_else(|| missing("pageInfo"))?,
+    )?;
+    let nodes = match (
+        connection.get_mut("nodes").map(Value::take),
+        connection.get_mut("edges").map(Value::take),
+    ) {
+        (Some(Value::Array(nodes)), _) => nodes,
+        (_, Some(Value::Array(edges))) => edges
+            .into_iter()
+            .filter_map(|mut edge| edge.get_mut("node").map(Value::take))
+            .collect(),
+        _ => return Err(missing("nodes or edges")),
+    };
+    Ok((nodes, page_info))
+}
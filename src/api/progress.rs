@@ -0,0 +1,144 @@
+use crate::{
+    error::CanvasError,
+    models::progress::{Progress, ProgressState},
+    Canvas, CanvasResult, PaginatedVec,
+};
+
+use futures::{future::BoxFuture, StreamExt};
+use std::future::IntoFuture;
+use std::time::Duration;
+
+const DEFAULT_INITIAL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_INTERVAL: Duration = Duration::from_secs(30);
+const INTERVAL_MULTIPLIER: f64 = 1.5;
+
+#[derive(Clone)]
+pub struct ProgressHandler {
+    canvas: Canvas,
+}
+impl ProgressHandler {
+    pub fn new(canvas: Canvas) -> Self {
+        Self { canvas }
+    }
+}
+
+impl ProgressHandler {
+    /// Get the current state of a background job.
+    pub async fn get(&self, progress_id: u64) -> CanvasResult<Progress> {
+        self.canvas
+            .get_endpoint(&format!("progress/{progress_id}"), None)
+            .await
+    }
+
+    /// Poll a background job until it has finished. See [`ProgressWatcher`].
+    pub fn watch(&self, progress_id: u64) -> ProgressWatcher {
+        ProgressWatcher {
+            handler: self.clone(),
+            progress_id,
+            initial_interval: DEFAULT_INITIAL_INTERVAL,
+            max_interval: DEFAULT_MAX_INTERVAL,
+        }
+    }
+}
+
+/// Polls a [`Progress`] until the job has completed or failed.
+///
+/// Awaiting the watcher resolves to the final state of a completed job, or
+/// [`CanvasError::JobFailed`] for a failed one. Use [`updates`](Self::updates)
+/// or [`percentages`](Self::percentages) to follow the job along the way.
+///
+/// The job is polled every second at first. While its completion does not
+/// change, the interval grows up to 30 seconds.
+pub struct ProgressWatcher {
+    handler: ProgressHandler,
+    progress_id: u64,
+    initial_interval: Duration,
+    max_interval: Duration,
+}
+
+impl ProgressWatcher {
+    /// How long to wait between polls at first, and again after every poll
+    /// that saw the job advance.
+    pub fn initial_interval(mut self, interval: Duration) -> Self {
+        self.initial_interval = interval;
+        self
+    }
+
+    /// Upper bound for the time between two polls.
+    pub fn max_interval(mut self, interval: Duration) -> Self {
+        self.max_interval = interval;
+        self
+    }
+
+    /// Stream the state of the job every time its completion or state
+    /// changes. The last item is the finished job, a failed job is reported
+    /// as [`CanvasError::JobFailed`].
+    pub fn updates(self) -> PaginatedVec<CanvasResult<Progress>> {
+        use async_stream::stream;
+
+        Box::pin(stream! {
+            let mut interval = self.initial_interval;
+            let mut last: Option<(ProgressState, Option<f64>)> = None;
+            loop {
+                let progress = self.handler.get(self.progress_id).await?;
+                let state = (progress.workflow_state, progress.completion);
+                let changed = last != Some(state);
+                last = Some(state);
+
+                match progress.workflow_state {
+                    ProgressState::Completed => {
+                        yield Ok(progress);
+                        break;
+                    }
+                    ProgressState::Failed => {
+                        yield Err(CanvasError::JobFailed(Box::new(progress)));
+                        break;
+                    }
+                    ProgressState::Queued | ProgressState::Running => {}
+                }
+
+                if changed {
+                    yield Ok(progress);
+                    interval = self.initial_interval;
+                } else {
+                    interval = interval
+                        .mul_f64(INTERVAL_MULTIPLIER)
+                        .min(self.max_interval.max(self.initial_interval));
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Stream the completion of the job in percent, every time it changes.
+    pub fn percentages(self) -> PaginatedVec<CanvasResult<f64>> {
+        Box::pin(
+            self.updates()
+                .map(|progress| progress.map(|p| p.completion.unwrap_or_default())),
+        )
+    }
+
+    /// Wait for the job to finish.
+    pub async fn wait(self) -> CanvasResult<Progress> {
+        let progress_id = self.progress_id;
+        let mut updates = self.updates();
+        while let Some(progress) = updates.next().await {
+            let progress = progress?;
+            if progress.is_finished() {
+                return Ok(progress);
+            }
+        }
+        // The updates end with the finished job or an error, so this is only
+        // reached if that changes.
+        Err(CanvasError::JobUnfinished(progress_id))
+    }
+}
+
+impl IntoFuture for ProgressWatcher {
+    type Output = CanvasResult<Progress>;
+    type IntoFuture = BoxFuture<'static, CanvasResult<Progress>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.wait())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{encoding::to_params, Canvas, CanvasResult, Page, PaginatedVec};
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+/// Marks a [`ListRequest`] as being sent from async code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Async;
+
+/// A request to a paginated endpoint, returning items of type `T`.
+///
+/// The endpoint specific options live in `P` and are set through the methods
+/// the handlers implement on their `ListRequest`s. Call [`send`](Self::send) to
+/// start streaming the results, or [`send_pages`](Self::send_pages) to stream
+/// them page by page.
+///
+/// `M` tells whether the request is sent from async code or through the
+/// blocking client, which decides what `send` returns.
+pub struct ListRequest<T, P, M = Async> {
+    canvas: Canvas,
+    endpoint: String,
+    pub(crate) params: P,
+    per_page: Option<u32>,
+    resume_from: Option<String>,
+    #[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+    pub(crate) mode: M,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T, P: Default> ListRequest<T, P> {
+    pub(crate) fn new(canvas: Canvas, endpoint: String) -> Self {
+        Self {
+            canvas,
+            endpoint,
+            params: Default::default(),
+            per_page: None,
+            resume_from: None,
+            mode: Async,
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<T, P, M> ListRequest<T, P, M> {
+    /// How many items Canvas should return per page. Canvas caps this at a
+    /// server defined maximum, usually 100.
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = Some(per_page);
+        self
+    }
+
+    /// Continue a previous listing at the page behind `next_url`, as found in
+    /// [`PaginationInfo::next_url`](crate::PaginationInfo::next_url).
+    ///
+    /// The URL already carries the query parameters of the original request,
+    /// so the other options of this request are ignored.
+    pub fn resume_from(mut self, next_url: impl Into<String>) -> Self {
+        self.resume_from = Some(next_url.into());
+        self
+    }
+
+    #[cfg_attr(not(feature = "blocking"), allow(dead_code))]
+    pub(crate) fn with_mode<N>(self, mode: N) -> ListRequest<T, P, N> {
+        ListRequest {
+            canvas: self.canvas,
+            endpoint: self.endpoint,
+            params: self.params,
+            per_page: self.per_page,
+            resume_from: self.resume_from,
+            mode,
+            _item: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static, P: Serialize, M> ListRequest<T, P, M> {
+    pub(crate) async fn stream_pages(self) -> PaginatedVec<CanvasResult<Page<T>>> {
+        if let Some(url) = &self.resume_from {
+            return self.canvas.stream_pages_from_url(url).await;
+        }
+
+        let mut params = match to_params(&self.params) {
+            Ok(params) => params,
+            Err(e) => return Box::pin(futures::stream::once(async { Err(e) })),
+        };
+        if let Some(per_page) = self.per_page {
+            params.push(("per_page".to_string(), per_page.to_string()));
+        }
+        self.canvas
+            .stream_pages_with_params(&self.endpoint, &params)
+            .await
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static, P: Serialize> ListRequest<T, P> {
+    pub async fn send(self) -> PaginatedVec<CanvasResult<T>> {
+        Canvas::flatten_pages(self.send_pages().await)
+    }
+
+    pub async fn send_pages(self) -> PaginatedVec<CanvasResult<Page<T>>> {
+        self.stream_pages().await
+    }
+}
@@ -1,25 +1,99 @@
-use crate::{models::users::User, Canvas, CanvasResult, PaginatedVec};
-pub struct UserHandler<'canvas> {
-    canvas: &'canvas Canvas,
+use super::{
+    files::{FileUpload, UploadSource},
+    request::{Async, ListRequest},
+};
+use crate::{
+    ids::{AccountId, UserId},
+    models::users::User,
+    params::{Order, UserEnrollmentType, UserInclude, UserSort},
+    Canvas, CanvasResult,
+};
+
+use serde::Serialize;
+
+#[derive(Clone)]
+pub struct UserHandler {
+    canvas: Canvas,
 }
-impl<'canvas> UserHandler<'canvas> {
-    pub fn new(canvas: &'canvas Canvas) -> Self {
+impl UserHandler {
+    pub fn new(canvas: Canvas) -> Self {
         Self { canvas }
     }
 }
 
-impl<'canvas> UserHandler<'canvas> {
+impl UserHandler {
     /// Get a specific user.
-    pub async fn get(self, user_id: u32) -> CanvasResult<User> {
+    pub async fn get(self, user_id: impl Into<UserId>) -> CanvasResult<User> {
+        let user_id = user_id.into().to_path_segment();
         self.canvas
             .get_endpoint(&format!("users/{user_id}"), None)
             .await
     }
 
-    /// List the active courses for a specific user.
-    pub async fn list_for_account(&self, account_id: u32) -> PaginatedVec<CanvasResult<User>> {
+    /// Upload a file to the personal files of a user.
+    ///
+    /// To upload an avatar, put the file into the `profile pictures` folder of
+    /// the current user with [`FileUpload::parent_folder_path`].
+    pub fn upload_file(&self, user_id: impl Into<UserId>, source: UploadSource) -> FileUpload {
+        let user_id = user_id.into().to_path_segment();
         self.canvas
-            .stream_endpoint(&format!("accounts/{account_id}/users"))
-            .await
+            .upload_file(format!("users/{user_id}/files"), source)
+    }
+
+    /// List the users associated with a specific account.
+    pub fn list_for_account(&self, account_id: impl Into<AccountId>) -> ListAccountUsers {
+        let account_id = account_id.into().to_path_segment();
+        ListRequest::new(self.canvas.clone(), format!("accounts/{account_id}/users"))
+    }
+}
+
+pub type ListAccountUsers<M = Async> = ListRequest<User, ListAccountUsersParams, M>;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ListAccountUsersParams {
+    search_term: Option<String>,
+    enrollment_type: Option<UserEnrollmentType>,
+    sort: Option<UserSort>,
+    order: Option<Order>,
+    include_deleted_users: Option<bool>,
+    include: Vec<UserInclude>,
+}
+
+impl<M> ListAccountUsers<M> {
+    /// Only return users whose name, login, email or SIS ID contains this term.
+    /// Canvas requires at least two characters.
+    pub fn search_term(mut self, term: impl Into<String>) -> Self {
+        self.params.search_term = Some(term.into());
+        self
+    }
+
+    /// Only return users enrolled as this type in at least one course.
+    pub fn enrollment_type(mut self, enrollment_type: UserEnrollmentType) -> Self {
+        self.params.enrollment_type = Some(enrollment_type);
+        self
+    }
+
+    /// The column to sort the users by.
+    pub fn sort(mut self, sort: UserSort) -> Self {
+        self.params.sort = Some(sort);
+        self
+    }
+
+    /// The direction to sort the users in.
+    pub fn order(mut self, order: Order) -> Self {
+        self.params.order = Some(order);
+        self
+    }
+
+    /// Include deleted users as well.
+    pub fn include_deleted_users(mut self, include: bool) -> Self {
+        self.params.include_deleted_users = Some(include);
+        self
+    }
+
+    /// Additional information to include with each user.
+    pub fn include(mut self, include: impl IntoIterator<Item = UserInclude>) -> Self {
+        self.params.include.extend(include);
+        self
     }
 }
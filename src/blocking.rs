@@ -0,0 +1,841 @@
+//! A blocking Canvas client, for programs that do not otherwise use async.
+//!
+//! It offers the same handlers as the async [`Canvas`](crate::Canvas) and
+//! returns the same models and errors, but paginated endpoints are returned as
+//! plain [`Iterator`]s instead of streams.
+//!
+//! The client runs the async one on a runtime of its own. It must not be used
+//! from within an async runtime, as blocking on a request there panics.
+use crate::{
+    api::{
+        accounts::{
+            AccountHandler as AsyncAccountHandler, ListAccountCourses, ListAccounts,
+            ListSubAccounts,
+        },
+        courses::{
+            CourseEvent, CourseHandler as AsyncCourseHandler, CourseSettings, ListCourses,
+            ListUserCourses, NewCourse,
+        },
+        files::{
+            Download as AsyncDownload, FileContext, FileHandler as AsyncFileHandler, FileUpdate,
+            FileUpload as AsyncFileUpload, ListFiles, OnDuplicate, UploadProgress, UploadSource,
+        },
+        folders::{
+            FolderHandler as AsyncFolderHandler, FolderUpdate, ListFolders, NewFolder, WalkedFile,
+        },
+        graphql::GraphQLHandler as AsyncGraphQLHandler,
+        progress::{
+            ProgressHandler as AsyncProgressHandler, ProgressWatcher as AsyncProgressWatcher,
+        },
+        request::ListRequest,
+        users::{ListAccountUsers, UserHandler as AsyncUserHandler},
+    },
+    cache::CacheStats,
+    error::ConfigError,
+    ids::{AccountId, CourseId, UserId},
+    models::{
+        accounts::Account,
+        courses::Course,
+        files::{File, FileQuota, Folder},
+        progress::Progress,
+        users::User,
+    },
+    CanvasBuilder, CanvasResult, Page, PaginatedVec, RequestBody,
+};
+
+use futures::StreamExt;
+use reqwest::{header::HeaderMap, Method};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+use tokio::runtime::Runtime;
+
+/// Marks a [`ListRequest`] as being sent through the blocking client.
+#[derive(Clone)]
+pub struct Blocking {
+    runtime: Arc<Runtime>,
+}
+
+/// The items of a paginated endpoint, fetched as the iterator advances.
+pub struct PaginatedIter<T> {
+    runtime: Arc<Runtime>,
+    stream: PaginatedVec<T>,
+}
+
+impl<T> Iterator for PaginatedIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+/// A blocking Canvas API client.
+///
+/// Cloning is cheap: all clones share the same runtime, connection pool, rate
+/// limit budget and configuration.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    inner: crate::Canvas,
+    runtime: Arc<Runtime>,
+}
+
+impl Canvas {
+    /// Create a client with the default settings.
+    ///
+    /// Use [`Canvas::builder`](crate::Canvas::builder) and
+    /// [`CanvasBuilder::build_blocking`] for anything else.
+    pub fn init(base_url: &str, api_token: &str) -> CanvasResult<Self> {
+        crate::Canvas::builder()
+            .base_url(base_url)
+            .token(api_token)
+            .build_blocking()
+    }
+
+    /// Wrap an async client, sharing its configuration and rate limit budget.
+    pub fn from_async(canvas: crate::Canvas) -> CanvasResult<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(ConfigError::Runtime)?;
+        Ok(Self {
+            inner: canvas,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// The async client this one wraps.
+    pub fn as_async(&self) -> &crate::Canvas {
+        &self.inner
+    }
+
+    /// See [`Canvas::as_user`](crate::Canvas::as_user).
+    pub fn as_user(&self, user_id: impl Into<UserId>) -> Canvas {
+        Canvas {
+            inner: self.inner.as_user(user_id),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// The user this client is acting as, if any.
+    pub fn masquerading_as(&self) -> Option<&str> {
+        self.inner.masquerading_as()
+    }
+
+    /// See [`Canvas::rate_limit_remaining`](crate::Canvas::rate_limit_remaining).
+    pub fn rate_limit_remaining(&self) -> Option<f64> {
+        self.inner.rate_limit_remaining()
+    }
+
+    /// See [`Canvas::cache_stats`](crate::Canvas::cache_stats).
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.inner.cache_stats()
+    }
+
+    fn mode(&self) -> Blocking {
+        Blocking {
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    fn iter<T>(&self, stream: PaginatedVec<T>) -> PaginatedIter<T> {
+        PaginatedIter {
+            runtime: self.runtime.clone(),
+            stream,
+        }
+    }
+
+    pub fn get_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        headers: Option<HeaderMap>,
+    ) -> CanvasResult<R> {
+        self.runtime
+            .block_on(self.inner.get_endpoint(endpoint, headers))
+    }
+
+    /// See [`Canvas::request_endpoint`](crate::Canvas::request_endpoint).
+    pub fn request_endpoint<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.runtime
+            .block_on(self.inner.request_endpoint(method, endpoint, body))
+    }
+
+    pub fn post_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_endpoint(Method::POST, endpoint, body)
+    }
+
+    pub fn put_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_endpoint(Method::PUT, endpoint, body)
+    }
+
+    pub fn delete_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_endpoint(Method::DELETE, endpoint, body)
+    }
+
+    pub fn stream_endpoint<T: DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &str,
+    ) -> PaginatedIter<CanvasResult<T>> {
+        self.stream_endpoint_with_params(endpoint, &[])
+    }
+
+    pub fn stream_endpoint_with_params<T: DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &str,
+        params: &[(String, String)],
+    ) -> PaginatedIter<CanvasResult<T>> {
+        let stream = self
+            .runtime
+            .block_on(self.inner.stream_endpoint_with_params(endpoint, params));
+        self.iter(stream)
+    }
+
+    /// See [`Canvas::stream_from_url`](crate::Canvas::stream_from_url).
+    pub fn stream_from_url<T: DeserializeOwned + Send + 'static>(
+        &self,
+        url: &str,
+    ) -> PaginatedIter<CanvasResult<T>> {
+        let stream = self.runtime.block_on(self.inner.stream_from_url(url));
+        self.iter(stream)
+    }
+
+    pub fn stream_pages<T: DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &str,
+    ) -> PaginatedIter<CanvasResult<Page<T>>> {
+        let stream = self.runtime.block_on(self.inner.stream_pages(endpoint));
+        self.iter(stream)
+    }
+
+    pub fn accounts(&self) -> AccountHandler {
+        AccountHandler {
+            inner: self.inner.accounts(),
+            canvas: self.clone(),
+        }
+    }
+
+    pub fn courses(&self) -> CourseHandler {
+        CourseHandler {
+            inner: self.inner.courses(),
+            canvas: self.clone(),
+        }
+    }
+
+    pub fn users(&self) -> UserHandler {
+        UserHandler {
+            inner: self.inner.users(),
+            canvas: self.clone(),
+        }
+    }
+
+    pub fn files(&self) -> FileHandler {
+        FileHandler {
+            inner: self.inner.files(),
+            canvas: self.clone(),
+        }
+    }
+
+    pub fn folders(&self) -> FolderHandler {
+        FolderHandler {
+            inner: self.inner.folders(),
+            canvas: self.clone(),
+        }
+    }
+}
+
+impl Canvas {
+    pub fn graphql(&self) -> GraphQLHandler {
+        GraphQLHandler {
+            inner: self.inner.graphql(),
+            canvas: self.clone(),
+        }
+    }
+
+    pub fn progress(&self) -> ProgressHandler {
+        ProgressHandler {
+            inner: self.inner.progress(),
+            canvas: self.clone(),
+        }
+    }
+}
+
+impl Canvas {
+    /// See [`Canvas::upload_file`](crate::Canvas::upload_file).
+    pub fn upload_file(&self, endpoint: impl Into<String>, source: UploadSource) -> FileUpload {
+        FileUpload {
+            inner: self.inner.upload_file(endpoint, source),
+            canvas: self.clone(),
+        }
+    }
+
+    /// See [`Canvas::download_file`](crate::Canvas::download_file).
+    pub fn download_file(&self, file: &File) -> Download {
+        Download {
+            inner: self.inner.download_file(file),
+            canvas: self.clone(),
+        }
+    }
+
+    /// See [`Canvas::download`](crate::Canvas::download).
+    pub fn download(&self, url: impl Into<String>) -> Download {
+        Download {
+            inner: self.inner.download(url),
+            canvas: self.clone(),
+        }
+    }
+}
+
+impl CanvasBuilder {
+    /// Build a [`blocking::Canvas`](Canvas) client instead of an async one.
+    pub fn build_blocking(self) -> CanvasResult<Canvas> {
+        Canvas::from_async(self.build()?)
+    }
+}
+
+impl<T: DeserializeOwned + Send + 'static, P: Serialize> ListRequest<T, P, Blocking> {
+    pub fn send(self) -> PaginatedIter<CanvasResult<T>> {
+        let runtime = self.mode.runtime.clone();
+        let pages = runtime.block_on(self.stream_pages());
+        PaginatedIter {
+            runtime,
+            stream: crate::Canvas::flatten_pages(pages),
+        }
+    }
+
+    pub fn send_pages(self) -> PaginatedIter<CanvasResult<Page<T>>> {
+        let runtime = self.mode.runtime.clone();
+        let stream = runtime.block_on(self.stream_pages());
+        PaginatedIter { runtime, stream }
+    }
+}
+
+#[derive(Clone)]
+pub struct AccountHandler {
+    inner: AsyncAccountHandler,
+    canvas: Canvas,
+}
+
+impl AccountHandler {
+    /// Get a specific account.
+    pub fn get(&self, account_id: impl Into<AccountId>) -> CanvasResult<Account> {
+        self.canvas.runtime.block_on(self.inner.get(account_id))
+    }
+
+    /// List the accounts the current user can view or manage.
+    pub fn list(&self) -> ListAccounts<Blocking> {
+        self.inner.list().with_mode(self.canvas.mode())
+    }
+
+    /// List the sub-accounts directly below an account.
+    pub fn sub_accounts(&self, account_id: impl Into<AccountId>) -> ListSubAccounts<Blocking> {
+        self.inner
+            .sub_accounts(account_id)
+            .with_mode(self.canvas.mode())
+    }
+
+    /// See [`AccountHandler::walk`](crate::api::accounts::AccountHandler::walk).
+    pub fn walk(&self, account_id: impl Into<AccountId>) -> PaginatedIter<CanvasResult<Account>> {
+        self.canvas.iter(self.inner.walk(account_id))
+    }
+
+    /// List the courses of an account and its sub-accounts.
+    pub fn courses(&self, account_id: impl Into<AccountId>) -> ListAccountCourses<Blocking> {
+        self.inner.courses(account_id).with_mode(self.canvas.mode())
+    }
+
+    /// Get the settings of an account.
+    pub fn settings(&self, account_id: impl Into<AccountId>) -> CanvasResult<Map<String, Value>> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.settings(account_id))
+    }
+
+    /// See [`AccountHandler::update_settings`](crate::api::accounts::AccountHandler::update_settings).
+    pub fn update_settings(
+        &self,
+        account_id: impl Into<AccountId>,
+        settings: Map<String, Value>,
+    ) -> CanvasResult<Account> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.update_settings(account_id, settings))
+    }
+}
+
+#[derive(Clone)]
+pub struct CourseHandler {
+    inner: AsyncCourseHandler,
+    canvas: Canvas,
+}
+
+impl CourseHandler {
+    /// Get a specific course.
+    pub fn get(self, course_id: impl Into<CourseId>) -> CanvasResult<Course> {
+        self.canvas.runtime.block_on(self.inner.get(course_id))
+    }
+
+    /// Upload a file to the files of a course.
+    pub fn upload_file(&self, course_id: impl Into<CourseId>, source: UploadSource) -> FileUpload {
+        FileUpload {
+            inner: self.inner.upload_file(course_id, source),
+            canvas: self.canvas.clone(),
+        }
+    }
+
+    /// Upload a file to attach to a submission of `user_id` for an
+    /// assignment.
+    pub fn upload_submission_file(
+        &self,
+        course_id: impl Into<CourseId>,
+        assignment_id: u64,
+        user_id: impl Into<UserId>,
+        source: UploadSource,
+    ) -> FileUpload {
+        FileUpload {
+            inner: self
+                .inner
+                .upload_submission_file(course_id, assignment_id, user_id, source),
+            canvas: self.canvas.clone(),
+        }
+    }
+
+    /// List the current user's active courses.
+    pub fn list(&self) -> ListCourses<Blocking> {
+        self.inner.list().with_mode(self.canvas.mode())
+    }
+
+    /// List the active courses for a specific user.
+    pub fn list_for_user(&self, user_id: impl Into<UserId>) -> ListUserCourses<Blocking> {
+        self.inner
+            .list_for_user(user_id)
+            .with_mode(self.canvas.mode())
+    }
+
+    /// Create a course in an account.
+    pub fn create(
+        &self,
+        account_id: impl Into<AccountId>,
+        course: NewCourse,
+    ) -> CanvasResult<Course> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.create(account_id, course))
+    }
+
+    /// See [`CourseHandler::update`](crate::api::courses::CourseHandler::update).
+    pub fn update(
+        &self,
+        course_id: impl Into<CourseId>,
+        settings: CourseSettings,
+        event: Option<CourseEvent>,
+    ) -> CanvasResult<Course> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.update(course_id, settings, event))
+    }
+
+    /// Delete a course.
+    pub fn delete(&self, course_id: impl Into<CourseId>) -> CanvasResult<()> {
+        self.canvas.runtime.block_on(self.inner.delete(course_id))
+    }
+
+    /// Conclude a course.
+    pub fn conclude(&self, course_id: impl Into<CourseId>) -> CanvasResult<()> {
+        self.canvas.runtime.block_on(self.inner.conclude(course_id))
+    }
+
+    /// See [`CourseHandler::reset_content`](crate::api::courses::CourseHandler::reset_content).
+    pub fn reset_content(&self, course_id: impl Into<CourseId>) -> CanvasResult<Course> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.reset_content(course_id))
+    }
+
+    /// See [`CourseHandler::batch_update`](crate::api::courses::CourseHandler::batch_update).
+    pub fn batch_update<C: Into<CourseId>>(
+        &self,
+        account_id: impl Into<AccountId>,
+        course_ids: impl IntoIterator<Item = C>,
+        event: CourseEvent,
+    ) -> CanvasResult<Progress> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.batch_update(account_id, course_ids, event))
+    }
+}
+
+#[derive(Clone)]
+pub struct UserHandler {
+    inner: AsyncUserHandler,
+    canvas: Canvas,
+}
+
+impl UserHandler {
+    /// Get a specific user.
+    pub fn get(self, user_id: impl Into<UserId>) -> CanvasResult<User> {
+        self.canvas.runtime.block_on(self.inner.get(user_id))
+    }
+
+    /// Upload a file to the personal files of a user.
+    pub fn upload_file(&self, user_id: impl Into<UserId>, source: UploadSource) -> FileUpload {
+        FileUpload {
+            inner: self.inner.upload_file(user_id, source),
+            canvas: self.canvas.clone(),
+        }
+    }
+
+    /// List the users associated with a specific account.
+    pub fn list_for_account(&self, account_id: impl Into<AccountId>) -> ListAccountUsers<Blocking> {
+        self.inner
+            .list_for_account(account_id)
+            .with_mode(self.canvas.mode())
+    }
+}
+
+#[derive(Clone)]
+pub struct FileHandler {
+    inner: AsyncFileHandler,
+    canvas: Canvas,
+}
+
+impl FileHandler {
+    /// Get a specific file.
+    pub fn get(&self, file_id: u64) -> CanvasResult<File> {
+        self.canvas.runtime.block_on(self.inner.get(file_id))
+    }
+
+    /// List the files of a course, group or user, from all of its folders.
+    pub fn list(&self, context: &FileContext) -> ListFiles<Blocking> {
+        self.inner.list(context).with_mode(self.canvas.mode())
+    }
+
+    /// List the files directly inside a folder.
+    pub fn list_in_folder(&self, folder_id: u64) -> ListFiles<Blocking> {
+        self.inner
+            .list_in_folder(folder_id)
+            .with_mode(self.canvas.mode())
+    }
+
+    /// See [`FileHandler::quota`](crate::api::files::FileHandler::quota).
+    pub fn quota(&self, context: &FileContext) -> CanvasResult<FileQuota> {
+        self.canvas.runtime.block_on(self.inner.quota(context))
+    }
+
+    /// Upload a file to a course, group or user.
+    pub fn upload(&self, context: &FileContext, source: UploadSource) -> FileUpload {
+        FileUpload {
+            inner: self.inner.upload(context, source),
+            canvas: self.canvas.clone(),
+        }
+    }
+
+    /// Rename, move, lock or hide a file.
+    pub fn update(&self, file_id: u64, update: FileUpdate) -> CanvasResult<File> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.update(file_id, update))
+    }
+
+    /// See [`FileHandler::copy`](crate::api::files::FileHandler::copy).
+    pub fn copy(
+        &self,
+        file_id: u64,
+        folder_id: u64,
+        on_duplicate: Option<OnDuplicate>,
+    ) -> CanvasResult<File> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.copy(file_id, folder_id, on_duplicate))
+    }
+
+    /// Delete a file, returning it as it was.
+    pub fn delete(&self, file_id: u64) -> CanvasResult<File> {
+        self.canvas.runtime.block_on(self.inner.delete(file_id))
+    }
+}
+
+#[derive(Clone)]
+pub struct FolderHandler {
+    inner: AsyncFolderHandler,
+    canvas: Canvas,
+}
+
+impl FolderHandler {
+    /// Get a specific folder.
+    pub fn get(&self, folder_id: u64) -> CanvasResult<Folder> {
+        self.canvas.runtime.block_on(self.inner.get(folder_id))
+    }
+
+    /// Get the root folder of a course, group or user.
+    pub fn root(&self, context: &FileContext) -> CanvasResult<Folder> {
+        self.canvas.runtime.block_on(self.inner.root(context))
+    }
+
+    /// See [`FolderHandler::resolve_path`](crate::api::folders::FolderHandler::resolve_path).
+    pub fn resolve_path(&self, context: &FileContext, path: &str) -> CanvasResult<Vec<Folder>> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.resolve_path(context, path))
+    }
+
+    /// List the folders directly inside a folder.
+    pub fn list(&self, folder_id: u64) -> ListFolders<Blocking> {
+        self.inner.list(folder_id).with_mode(self.canvas.mode())
+    }
+
+    /// List all folders of a course, group or user, flattened.
+    pub fn list_all(&self, context: &FileContext) -> ListFolders<Blocking> {
+        self.inner.list_all(context).with_mode(self.canvas.mode())
+    }
+
+    /// Create a folder in a course, group or user.
+    pub fn create(&self, context: &FileContext, folder: NewFolder) -> CanvasResult<Folder> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.create(context, folder))
+    }
+
+    /// Rename, move, lock or hide a folder.
+    pub fn update(&self, folder_id: u64, update: FolderUpdate) -> CanvasResult<Folder> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.update(folder_id, update))
+    }
+
+    /// Copy a folder and everything in it into another folder.
+    pub fn copy(&self, folder_id: u64, to_folder_id: u64) -> CanvasResult<Folder> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.copy(folder_id, to_folder_id))
+    }
+
+    /// See [`FolderHandler::delete`](crate::api::folders::FolderHandler::delete).
+    pub fn delete(&self, folder_id: u64, force: bool) -> CanvasResult<Folder> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.delete(folder_id, force))
+    }
+
+    /// See [`FolderHandler::walk`](crate::api::folders::FolderHandler::walk).
+    pub fn walk(&self, folder_id: u64) -> PaginatedIter<CanvasResult<WalkedFile>> {
+        self.canvas.iter(self.inner.walk(folder_id))
+    }
+}
+
+#[derive(Clone)]
+pub struct GraphQLHandler {
+    inner: AsyncGraphQLHandler,
+    canvas: Canvas,
+}
+
+impl GraphQLHandler {
+    /// See [`GraphQLHandler::query`](crate::api::graphql::GraphQLHandler::query).
+    pub fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: impl Serialize,
+    ) -> CanvasResult<T> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.query(query, variables))
+    }
+
+    /// See [`GraphQLHandler::paginate`](crate::api::graphql::GraphQLHandler::paginate).
+    pub fn paginate<T: DeserializeOwned + Send + 'static>(
+        &self,
+        query: &str,
+        variables: impl Serialize,
+        connection: &[&str],
+    ) -> PaginatedIter<CanvasResult<T>> {
+        self.canvas
+            .iter(self.inner.paginate(query, variables, connection))
+    }
+}
+
+#[derive(Clone)]
+pub struct ProgressHandler {
+    inner: AsyncProgressHandler,
+    canvas: Canvas,
+}
+
+impl ProgressHandler {
+    /// Get the current state of a background job.
+    pub fn get(&self, progress_id: u64) -> CanvasResult<Progress> {
+        self.canvas.runtime.block_on(self.inner.get(progress_id))
+    }
+
+    /// Poll a background job until it has finished. See [`ProgressWatcher`].
+    pub fn watch(&self, progress_id: u64) -> ProgressWatcher {
+        ProgressWatcher {
+            inner: self.inner.watch(progress_id),
+            canvas: self.canvas.clone(),
+        }
+    }
+}
+
+/// See [`ProgressWatcher`](crate::api::progress::ProgressWatcher).
+pub struct ProgressWatcher {
+    inner: AsyncProgressWatcher,
+    canvas: Canvas,
+}
+
+impl ProgressWatcher {
+    pub fn initial_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.initial_interval(interval);
+        self
+    }
+
+    pub fn max_interval(mut self, interval: Duration) -> Self {
+        self.inner = self.inner.max_interval(interval);
+        self
+    }
+
+    pub fn updates(self) -> PaginatedIter<CanvasResult<Progress>> {
+        self.canvas.iter(self.inner.updates())
+    }
+
+    pub fn percentages(self) -> PaginatedIter<CanvasResult<f64>> {
+        self.canvas.iter(self.inner.percentages())
+    }
+
+    /// Block until the job has finished.
+    pub fn wait(self) -> CanvasResult<Progress> {
+        self.canvas.runtime.block_on(self.inner.wait())
+    }
+}
+
+/// See [`FileUpload`](crate::api::files::FileUpload).
+pub struct FileUpload {
+    inner: AsyncFileUpload,
+    canvas: Canvas,
+}
+
+impl FileUpload {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.inner = self.inner.name(name);
+        self
+    }
+
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.inner = self.inner.content_type(content_type);
+        self
+    }
+
+    pub fn parent_folder_id(mut self, folder_id: u64) -> Self {
+        self.inner = self.inner.parent_folder_id(folder_id);
+        self
+    }
+
+    pub fn parent_folder_path(mut self, path: impl Into<String>) -> Self {
+        self.inner = self.inner.parent_folder_path(path);
+        self
+    }
+
+    pub fn on_duplicate(mut self, on_duplicate: OnDuplicate) -> Self {
+        self.inner = self.inner.on_duplicate(on_duplicate);
+        self
+    }
+
+    pub fn on_progress(
+        mut self,
+        callback: impl Fn(UploadProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.inner = self.inner.on_progress(callback);
+        self
+    }
+
+    /// Run all three steps of the upload.
+    pub fn send(self) -> CanvasResult<File> {
+        self.canvas.runtime.block_on(self.inner.send())
+    }
+}
+
+/// See [`Download`](crate::api::files::Download).
+pub struct Download {
+    inner: AsyncDownload,
+    canvas: Canvas,
+}
+
+impl Download {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.inner = self.inner.name(name);
+        self
+    }
+
+    pub fn size(mut self, size: u64) -> Self {
+        self.inner = self.inner.size(size);
+        self
+    }
+
+    pub fn resume_from(mut self, offset: u64) -> Self {
+        self.inner = self.inner.resume_from(offset);
+        self
+    }
+
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.inner = self.inner.resume(resume);
+        self
+    }
+
+    /// Write the file into `writer`, returning its size.
+    pub fn to_writer<W: Write + Unpin + ?Sized>(self, writer: &mut W) -> CanvasResult<u64> {
+        self.canvas
+            .runtime
+            .block_on(self.inner.to_writer(&mut SyncWriter(writer)))
+    }
+
+    /// Save the file at `path`, returning its size.
+    pub fn to_path(self, path: impl AsRef<Path>) -> CanvasResult<u64> {
+        self.canvas.runtime.block_on(self.inner.to_path(path))
+    }
+
+    /// Save the file into the directory `dir`, returning its path.
+    pub fn to_dir(self, dir: impl AsRef<Path>) -> CanvasResult<PathBuf> {
+        self.canvas.runtime.block_on(self.inner.to_dir(dir))
+    }
+}
+
+// Lets the async download write into a blocking writer. Writes block the
+// runtime, which has nothing else to do meanwhile.
+struct SyncWriter<'a, W: ?Sized>(&'a mut W);
+
+impl<W: Write + Unpin + ?Sized> AsyncWrite for SyncWriter<'_, W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(self.get_mut().0.write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(self.get_mut().0.flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,461 @@
+use crate::{
+    cache::{HttpCache, ResponseCache},
+    error::ConfigError,
+    middleware::Middleware,
+    oauth::{OAuthClient, OAuthSession, TokenStore},
+    rate_limit::{RateLimitConfig, RateLimiter},
+    retry::RetryPolicy,
+    Authenticator, Canvas, CanvasInner, CanvasResult, Credentials,
+};
+
+use reqwest::header::HeaderValue;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+const DEFAULT_API_VERSION: u32 = 1;
+const DEFAULT_PAGE_CONCURRENCY: usize = 4;
+
+/// Builder for a [`Canvas`] client.
+///
+/// Created through [`Canvas::builder`]. At minimum a base URL and either an API
+/// token or OAuth2 credentials have to be given, everything else falls back to
+/// sensible defaults.
+#[derive(Default)]
+pub struct CanvasBuilder {
+    base_url: Option<String>,
+    credentials: Option<BuilderCredentials>,
+    api_version: Option<u32>,
+    allow_http: bool,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    proxies: Vec<reqwest::Proxy>,
+    no_proxy: bool,
+    root_certificates: Vec<reqwest::Certificate>,
+    built_in_root_certificates: Option<bool>,
+    rate_limit: Option<RateLimitConfig>,
+    no_rate_limit: bool,
+    retry_policy: Option<RetryPolicy>,
+    per_page: Option<u32>,
+    page_concurrency: Option<usize>,
+    response_cache: Option<Arc<dyn ResponseCache>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    allow_foreign_urls: bool,
+}
+
+// The credentials as given to the builder, checked and turned into
+// `Credentials` by `build`.
+enum BuilderCredentials {
+    Token(String),
+    OAuth(OAuthClient, Arc<dyn TokenStore>),
+}
+
+impl CanvasBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The root URL of the Canvas instance, e.g. `https://canvas.example.edu`.
+    ///
+    /// The URL must not contain the API path (`/api/v1`), use
+    /// [`api_version`](Self::api_version) instead.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// The access token used to authenticate against the API.
+    pub fn token(mut self, api_token: impl Into<String>) -> Self {
+        self.credentials = Some(BuilderCredentials::Token(api_token.into()));
+        self
+    }
+
+    /// Authenticate through OAuth2 with the tokens kept in `store`, refreshing
+    /// them when they expire. Replaces any token set with
+    /// [`token`](Self::token).
+    pub fn oauth(mut self, client: OAuthClient, store: Arc<dyn TokenStore>) -> Self {
+        self.credentials = Some(BuilderCredentials::OAuth(client, store));
+        self
+    }
+
+    /// The version of the REST API to talk to. Defaults to `1`.
+    pub fn api_version(mut self, version: u32) -> Self {
+        self.api_version = Some(version);
+        self
+    }
+
+    /// Allow a plain `http://` base URL, for the API as well as for the
+    /// [`OAuthClient`] passed to [`oauth`](Self::oauth).
+    ///
+    /// This is only meant for local development instances, as the token is
+    /// sent in clear text.
+    pub fn allow_http(mut self, allow: bool) -> Self {
+        self.allow_http = allow;
+        self
+    }
+
+    /// Allow requests to absolute URLs outside the Canvas instance, e.g. file
+    /// links pointing at a storage service. They are sent without the token.
+    ///
+    /// By default such requests fail with
+    /// [`CanvasError::ForeignUrl`](crate::error::CanvasError::ForeignUrl).
+    pub fn allow_foreign_urls(mut self, allow: bool) -> Self {
+        self.allow_foreign_urls = allow;
+        self
+    }
+
+    /// Total timeout for each request, from connecting until the response
+    /// body has been read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for establishing a connection.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Custom `User-Agent` header sent with every request.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Route requests through a proxy. Can be called multiple times.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Ignore any proxies configured through the environment.
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Trust an additional root certificate, e.g. for an instance behind a
+    /// private CA. Can be called multiple times.
+    pub fn add_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Whether to trust the platform's built-in root certificates. Defaults to
+    /// `true`.
+    pub fn built_in_root_certificates(mut self, enabled: bool) -> Self {
+        self.built_in_root_certificates = Some(enabled);
+        self
+    }
+
+    /// Tune how the client throttles itself to stay within the rate limit.
+    pub fn rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limit = Some(config);
+        self.no_rate_limit = false;
+        self
+    }
+
+    /// Send requests as fast as possible, without tracking the rate limit.
+    pub fn no_rate_limit(mut self) -> Self {
+        self.no_rate_limit = true;
+        self
+    }
+
+    /// Decide when and how often failed requests are retried. Use
+    /// [`RetryPolicy::never`] to turn retries off.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// How many items to request per page when streaming paginated endpoints,
+    /// unless a request sets its own. Larger pages mean fewer requests; Canvas
+    /// caps this at a server defined maximum, usually 100.
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = Some(per_page);
+        self
+    }
+
+    /// How many pages of a paginated endpoint may be fetched at the same time.
+    /// Defaults to 4, `1` fetches one page after another.
+    ///
+    /// This only applies to endpoints that number their pages; the requests
+    /// still go through the rate limiter.
+    pub fn page_concurrency(mut self, concurrency: usize) -> Self {
+        self.page_concurrency = Some(concurrency);
+        self
+    }
+
+    /// Revalidate `GET` responses against `cache` with conditional requests,
+    /// serving them from it when Canvas reports them unchanged. See the
+    /// [`cache`](crate::cache) module.
+    pub fn response_cache(mut self, cache: Arc<dyn ResponseCache>) -> Self {
+        self.response_cache = Some(cache);
+        self
+    }
+
+    /// Pass every request through `middleware`, e.g. to add headers or record
+    /// metrics. Can be called multiple times; the middleware runs in the order
+    /// it was added. See the [`middleware`](crate::middleware) module.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    pub fn build(self) -> CanvasResult<Canvas> {
+        let base_url = normalise_base_url(
+            self.base_url
+                .as_deref()
+                .ok_or(ConfigError::MissingBaseUrl)?,
+            self.allow_http,
+        )?;
+        let api_version = self.api_version.unwrap_or(DEFAULT_API_VERSION);
+        if api_version == 0 {
+            return Err(ConfigError::InvalidApiVersion.into());
+        }
+
+        let credentials = self.credentials.ok_or(ConfigError::MissingToken)?;
+
+        let origin = Url::parse(&base_url)
+            .map_err(|source| ConfigError::InvalidUrl {
+                url: base_url.clone(),
+                source,
+            })?
+            .origin();
+
+        // Redirects are followed by the client itself, see `redirect`.
+        let mut client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        if let Some(user_agent) = self.user_agent {
+            let user_agent =
+                HeaderValue::from_str(&user_agent).map_err(|_| ConfigError::InvalidUserAgent)?;
+            client = client.user_agent(user_agent);
+        }
+        if self.no_proxy {
+            client = client.no_proxy();
+        }
+        for proxy in self.proxies {
+            client = client.proxy(proxy);
+        }
+        for certificate in self.root_certificates {
+            client = client.add_root_certificate(certificate);
+        }
+        if let Some(enabled) = self.built_in_root_certificates {
+            client = client.tls_built_in_root_certs(enabled);
+        }
+
+        let client = client.build()?;
+        let credentials = match credentials {
+            BuilderCredentials::Token(api_token) => {
+                let mut authorization = HeaderValue::from_str(&format!("Bearer {}", api_token))
+                    .map_err(|_| ConfigError::InvalidToken)?;
+                authorization.set_sensitive(true);
+                Credentials::Token(authorization)
+            }
+            BuilderCredentials::OAuth(mut oauth, store) => {
+                // The OAuth client accepts any scheme, so that it can be used
+                // on its own against development instances.
+                normalise_base_url(oauth.base_url(), self.allow_http)?;
+                // Talk to the token endpoint through the same proxies etc.
+                oauth.http = client.clone();
+                Credentials::OAuth(OAuthSession::new(oauth, store))
+            }
+        };
+
+        let rate_limiter = (!self.no_rate_limit)
+            .then(|| Arc::new(RateLimiter::new(self.rate_limit.unwrap_or_default())));
+        let cache = self
+            .response_cache
+            .map(|store| Arc::new(HttpCache::new(store)));
+
+        let retry_policy = Arc::new(self.retry_policy.unwrap_or_default());
+        let mut middleware: Vec<Arc<dyn Middleware>> = vec![retry_policy.clone()];
+        #[cfg(feature = "tracing")]
+        middleware.push(Arc::new(crate::trace::Trace));
+        middleware.extend(self.middleware);
+        middleware.push(Arc::new(Authenticator {
+            credentials,
+            origin: origin.clone(),
+        }));
+        if let Some(cache) = &cache {
+            middleware.push(cache.clone());
+        }
+        if let Some(limiter) = &rate_limiter {
+            middleware.push(limiter.clone());
+        }
+
+        let inner = CanvasInner {
+            client,
+            base_url,
+            api_version,
+            origin,
+            allow_foreign_urls: self.allow_foreign_urls,
+            rate_limiter,
+            cache,
+            retry_policy,
+            middleware,
+            per_page: self.per_page,
+            page_concurrency: self
+                .page_concurrency
+                .unwrap_or(DEFAULT_PAGE_CONCURRENCY)
+                .max(1),
+        };
+        Ok(Canvas {
+            inner: Arc::new(inner),
+            masquerade: None,
+        })
+    }
+}
+
+/// Validate the user-supplied base URL and bring it into the form
+/// `scheme://host[:port][/path]`, without a trailing slash.
+///
+/// See the `Canvas` object at https://github.com/ucfopen/canvasapi/ for the
+/// checks this mirrors.
+pub(crate) fn normalise_base_url(base_url: &str, allow_http: bool) -> Result<String, ConfigError> {
+    let trimmed = base_url.trim();
+    if trimmed.is_empty() {
+        return Err(ConfigError::EmptyBaseUrl);
+    }
+    if !trimmed.contains("://") {
+        return Err(ConfigError::MissingScheme(trimmed.to_string()));
+    }
+
+    let url = Url::parse(trimmed).map_err(|source| ConfigError::InvalidUrl {
+        url: trimmed.to_string(),
+        source,
+    })?;
+    match url.scheme() {
+        "https" => {}
+        "http" if allow_http => {}
+        "http" => return Err(ConfigError::InsecureScheme(trimmed.to_string())),
+        _ => return Err(ConfigError::UnsupportedScheme(trimmed.to_string())),
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(ConfigError::UnexpectedQuery(trimmed.to_string()));
+    }
+
+    let path = url.path().trim_end_matches('/');
+    if path
+        .split('/')
+        .any(|segment| segment.eq_ignore_ascii_case("api"))
+    {
+        return Err(ConfigError::ContainsApiPath(trimmed.to_string()));
+    }
+
+    let origin = url.origin().ascii_serialization();
+    Ok(format!("{origin}{path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalises_valid_base_urls() {
+        let cases = [
+            (
+                "https://canvas.example.edu",
+                false,
+                "https://canvas.example.edu",
+            ),
+            (
+                "https://canvas.example.edu/",
+                false,
+                "https://canvas.example.edu",
+            ),
+            (
+                "  https://canvas.example.edu//  ",
+                false,
+                "https://canvas.example.edu",
+            ),
+            (
+                "HTTPS://Canvas.Example.edu:443/",
+                false,
+                "https://canvas.example.edu",
+            ),
+            ("https://host/canvas/", false, "https://host/canvas"),
+            (
+                "https://host:8443/canvas",
+                false,
+                "https://host:8443/canvas",
+            ),
+            ("http://localhost:3000/", true, "http://localhost:3000"),
+            ("https://host/apiary", false, "https://host/apiary"),
+        ];
+        for (input, allow_http, expected) in cases {
+            assert_eq!(
+                normalise_base_url(input, allow_http).unwrap(),
+                expected,
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_base_urls() {
+        let any = String::new;
+        let cases = [
+            ("", false, ConfigError::EmptyBaseUrl),
+            (" \t ", false, ConfigError::EmptyBaseUrl),
+            (
+                "canvas.example.edu",
+                false,
+                ConfigError::MissingScheme(any()),
+            ),
+            (
+                "http://canvas.example.edu",
+                false,
+                ConfigError::InsecureScheme(any()),
+            ),
+            (
+                "ftp://canvas.example.edu",
+                true,
+                ConfigError::UnsupportedScheme(any()),
+            ),
+            (
+                "https://canvas.example.edu/api/v1",
+                false,
+                ConfigError::ContainsApiPath(any()),
+            ),
+            (
+                "https://host/canvas/API/",
+                false,
+                ConfigError::ContainsApiPath(any()),
+            ),
+            (
+                "https://canvas.example.edu/?page=1",
+                false,
+                ConfigError::UnexpectedQuery(any()),
+            ),
+            (
+                "https://canvas.example.edu/#top",
+                false,
+                ConfigError::UnexpectedQuery(any()),
+            ),
+            (
+                "https://",
+                false,
+                ConfigError::InvalidUrl {
+                    url: any(),
+                    source: url::ParseError::EmptyHost,
+                },
+            ),
+        ];
+        for (input, allow_http, expected) in cases {
+            let error = normalise_base_url(input, allow_http).unwrap_err();
+            assert_eq!(
+                std::mem::discriminant(&error),
+                std::mem::discriminant(&expected),
+                "{input}: {error:?}"
+            );
+        }
+    }
+}
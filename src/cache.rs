@@ -0,0 +1,381 @@
+//! Conditional `GET` requests backed by a response cache.
+//!
+//! When a cache is configured through
+//! [`CanvasBuilder::response_cache`](crate::CanvasBuilder::response_cache),
+//! responses carrying an `ETag` or `Last-Modified` header are stored, and later
+//! requests for the same URL send `If-None-Match` / `If-Modified-Since`. If
+//! Canvas answers `304 Not Modified`, the stored body is served instead. A 304
+//! still counts against the rate limit, but saves transferring and decoding the
+//! payload again.
+//!
+//! Entries are keyed by URL only. Do not share a cache between clients
+//! authenticated as different users, as they would see each other's
+//! responses.
+use crate::{
+    error::ApiError,
+    middleware::{Middleware, Next},
+    CanvasResult,
+};
+
+use futures::future::BoxFuture;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, LAST_MODIFIED, LINK, RANGE,
+    },
+    Method, Request, Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// A stored response, along with the validators to revalidate it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+    // Kept so that cached pages of paginated endpoints still link to the next.
+    pub link: Option<String>,
+    // Not serialized, as JSON would bloat it; [`DiskCache`] stores it raw.
+    #[serde(skip)]
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    /// Extract what is worth caching from a successful response, or `None`
+    /// if it carries no validators.
+    fn from_parts(headers: &HeaderMap, body: &[u8]) -> Option<Self> {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(Self {
+            etag,
+            last_modified,
+            content_type: header(CONTENT_TYPE),
+            link: header(LINK),
+            body: body.to_vec(),
+        })
+    }
+}
+
+/// Where responses are kept between requests.
+///
+/// Implementations should swallow their own errors (e.g. an unwritable cache
+/// directory) and behave as if the entry was missing, so that a broken cache
+/// never fails a request.
+pub trait ResponseCache: Send + Sync {
+    /// The entry stored for `url`, if any.
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CachedResponse>>;
+    /// Store `response` for `url`, replacing any previous entry.
+    fn put<'a>(&'a self, url: &'a str, response: CachedResponse) -> BoxFuture<'a, ()>;
+}
+
+/// A [`ResponseCache`] in memory, evicting the least recently used entries
+/// once it holds `capacity` of them.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    state: Mutex<LruState>,
+}
+
+#[derive(Debug, Default)]
+struct LruState {
+    entries: HashMap<String, (CachedResponse, u64)>,
+    // Keys by the tick they were last used at, oldest first.
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl LruState {
+    fn touch(&mut self, url: &str) -> Option<&CachedResponse> {
+        self.tick += 1;
+        let (response, used) = self.entries.get_mut(url)?;
+        self.recency.remove(used);
+        *used = self.tick;
+        self.recency.insert(self.tick, url.to_string());
+        Some(response)
+    }
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Default::default(),
+        }
+    }
+}
+
+impl ResponseCache for MemoryCache {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CachedResponse>> {
+        let response = self.state.lock().unwrap().touch(url).cloned();
+        Box::pin(async { response })
+    }
+
+    fn put<'a>(&'a self, url: &'a str, response: CachedResponse) -> BoxFuture<'a, ()> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((_, used)) = state.entries.insert(url.to_string(), (response, tick)) {
+            state.recency.remove(&used);
+        }
+        state.recency.insert(tick, url.to_string());
+        while state.entries.len() > self.capacity {
+            let Some((_, oldest)) = state.recency.pop_first() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        Box::pin(async {})
+    }
+}
+
+/// A [`ResponseCache`] storing one file per URL in a directory, so that it
+/// survives restarts.
+///
+/// Each file holds a line of JSON with the validators, followed by the raw
+/// body. Nothing is ever evicted; clear the directory to reclaim space.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    url: String,
+    #[serde(flatten)]
+    response: CachedResponse,
+}
+
+impl DiskCache {
+    /// Cache into `dir`, which is created on the first write if needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(url.as_bytes())))
+    }
+
+    async fn read(&self, url: &str) -> Option<CachedResponse> {
+        let contents = tokio::fs::read(self.path(url)).await.ok()?;
+        let split = contents.iter().position(|&c| c == b'\n')?;
+        let entry: DiskEntry = serde_json::from_slice(&contents[..split]).ok()?;
+        // Different URLs can hash to the same file.
+        if entry.url != url {
+            return None;
+        }
+        Some(CachedResponse {
+            body: contents[split + 1..].to_vec(),
+            ..entry.response
+        })
+    }
+
+    async fn write(&self, url: &str, response: CachedResponse) -> std::io::Result<()> {
+        let entry = DiskEntry {
+            url: url.to_string(),
+            response,
+        };
+        let mut contents = serde_json::to_vec(&entry)?;
+        contents.push(b'\n');
+        contents.extend_from_slice(&entry.response.body);
+
+        // Write to a temporary file first, so that readers never see half an
+        // entry.
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(url);
+        let temporary = path.with_extension(format!("{}.tmp", fastrand::u32(..)));
+        tokio::fs::write(&temporary, contents).await?;
+        tokio::fs::rename(&temporary, &path).await
+    }
+}
+
+impl ResponseCache for DiskCache {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CachedResponse>> {
+        Box::pin(self.read(url))
+    }
+
+    fn put<'a>(&'a self, url: &'a str, response: CachedResponse) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let _ = self.write(url, response).await;
+        })
+    }
+}
+
+// 64 bit FNV-1a, which unlike the std hashers is stable across releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// How often cached responses could be reused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Requests answered with `304 Not Modified` and served from the cache.
+    pub hits: u64,
+    /// Requests that downloaded the full response.
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The share of requests served from the cache, between 0 and 1.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// The cache of a client, along with its statistics.
+pub(crate) struct HttpCache {
+    store: Arc<dyn ResponseCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HttpCache {
+    pub(crate) fn new(store: Arc<dyn ResponseCache>) -> Self {
+        Self {
+            store,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// The conditional headers to revalidate `cached` with.
+    fn conditional_headers(cached: &CachedResponse) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let validators = [
+            (IF_NONE_MATCH, &cached.etag),
+            (IF_MODIFIED_SINCE, &cached.last_modified),
+        ];
+        for (name, value) in validators {
+            if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+
+    /// Rebuild the response for a `304 Not Modified` from the cache.
+    fn serve(&self, cached: CachedResponse, url: Url, not_modified: &ApiError) -> Response {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        let mut headers = HeaderMap::new();
+        let stored = [
+            (CONTENT_TYPE, &cached.content_type),
+            (LINK, &cached.link),
+            (ETAG, &cached.etag),
+            (LAST_MODIFIED, &cached.last_modified),
+        ];
+        for (name, value) in stored {
+            if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        // Anything Canvas sent along with the 304 is more recent, e.g. the
+        // rate limit headers.
+        for (name, value) in &not_modified.headers {
+            headers.insert(name, value.clone());
+        }
+        headers.remove(CONTENT_LENGTH);
+        rebuild(StatusCode::OK, headers, url, cached.body)
+    }
+
+    /// Store a successful JSON response if it carries validators. As this has
+    /// to read the body, the response is rebuilt from it afterwards; anything
+    /// else, like file downloads, is passed on untouched to be streamed.
+    async fn store(&self, url: &str, resp: Response) -> CanvasResult<Response> {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let headers = resp.headers();
+        let is_json = headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("json"));
+        if !is_json || !headers.contains_key(ETAG) && !headers.contains_key(LAST_MODIFIED) {
+            return Ok(resp);
+        }
+
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let response_url = resp.url().clone();
+        let body = resp.bytes().await?;
+        if let Some(cached) = CachedResponse::from_parts(&headers, &body) {
+            self.store.put(url, cached).await;
+        }
+        Ok(rebuild(status, headers, response_url, body.to_vec()))
+    }
+}
+
+impl Middleware for HttpCache {
+    fn handle<'a>(
+        &'a self,
+        mut req: Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, CanvasResult<Response>> {
+        Box::pin(async move {
+            // Only plain GETs are cached; callers sending their own validators
+            // handle `304 Not Modified` themselves, ranges are partial, and
+            // downloads are streamed instead of read into memory.
+            let headers = req.headers();
+            if next.uncached
+                || req.method() != Method::GET
+                || headers.contains_key(IF_NONE_MATCH)
+                || headers.contains_key(IF_MODIFIED_SINCE)
+                || headers.contains_key(RANGE)
+            {
+                return next.run(req).await;
+            }
+
+            let url = req.url().clone();
+            let cached = self.store.get(url.as_str()).await;
+            if let Some(cached) = &cached {
+                req.headers_mut().extend(Self::conditional_headers(cached));
+            }
+
+            match (next.run(req).await, cached) {
+                (Ok(resp), _) => self.store(url.as_str(), resp).await,
+                (Err(e), Some(cached)) if e.status() == Some(StatusCode::NOT_MODIFIED) => {
+                    match e.api_error() {
+                        Some(not_modified) => Ok(self.serve(cached, url, not_modified)),
+                        None => Err(e),
+                    }
+                }
+                (Err(e), _) => Err(e),
+            }
+        })
+    }
+}
+
+fn rebuild(status: StatusCode, headers: HeaderMap, url: Url, body: Vec<u8>) -> Response {
+    use reqwest::ResponseBuilderExt;
+
+    let mut builder = http::Response::builder().status(status).url(url);
+    if let Some(builder_headers) = builder.headers_mut() {
+        *builder_headers = headers;
+    }
+    builder
+        .body(body)
+        .expect("status and headers come from a valid response")
+        .into()
+}
@@ -0,0 +1,168 @@
+use crate::{error::CanvasError, CanvasResult};
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// The body of a request that modifies data.
+#[derive(Debug, Clone, Default)]
+pub enum RequestBody {
+    #[default]
+    Empty,
+    /// `application/x-www-form-urlencoded` parameters, see [`to_params`].
+    Form(Vec<(String, String)>),
+    /// A JSON document.
+    Json(Value),
+}
+
+impl RequestBody {
+    /// Encode `value` as Rails-style form parameters.
+    pub fn form<T: Serialize + ?Sized>(value: &T) -> CanvasResult<Self> {
+        Ok(Self::Form(to_params(value)?))
+    }
+
+    /// Encode `value` as a JSON document.
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> CanvasResult<Self> {
+        Ok(Self::Json(serde_json::to_value(value)?))
+    }
+
+    pub(crate) fn apply(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            RequestBody::Empty => req,
+            RequestBody::Form(params) => req.form(params),
+            RequestBody::Json(value) => req.json(value),
+        }
+    }
+}
+
+/// Flatten `value` into the bracketed parameter names Canvas (being a Rails
+/// application) expects, e.g.
+///
+/// - `{"course": {"name": "Maths"}}` becomes `course[name]=Maths`
+/// - `{"include": ["term", "teachers"]}` becomes `include[]=term&include[]=teachers`
+/// - `{"grades": [{"user_id": 1}, {"user_id": 2}]}` becomes
+///   `grades[][user_id]=1&grades[][user_id]=2`
+///
+/// `None`/`null` values and empty arrays are left out entirely. The top level
+/// value has to serialize to a map, i.e. be a struct or a map.
+///
+/// Rails starts a new element of an array of maps only when a field repeats,
+/// and fields come out sorted, so `[{"b": 2}, {"a": 1, "b": 3}]` would be read
+/// back as `[{"b": 2, "a": 1}, {"b": 3}]`. Arrays whose maps differ in the
+/// fields they send, counting `null`s as missing, are therefore refused with
+/// [`CanvasError::Encoding`]; send such bodies with [`RequestBody::json`].
+pub fn to_params<T: Serialize + ?Sized>(value: &T) -> CanvasResult<Vec<(String, String)>> {
+    let Value::Object(map) = serde_json::to_value(value)? else {
+        return Err(CanvasError::Encoding(
+            "parameters must serialize to a map".to_string(),
+        ));
+    };
+
+    let mut params = Vec::new();
+    for (key, value) in map {
+        flatten(key, value, &mut params)?;
+    }
+    Ok(params)
+}
+
+fn flatten(key: String, value: Value, params: &mut Vec<(String, String)>) -> CanvasResult<()> {
+    match value {
+        Value::Null => {}
+        Value::Bool(b) => params.push((key, b.to_string())),
+        Value::Number(n) => params.push((key, n.to_string())),
+        Value::String(s) => params.push((key, s)),
+        Value::Array(items) => {
+            let key = format!("{key}[]");
+            // The parameter names sent for the first map in the array, which
+            // every other map has to match.
+            let mut fields: Option<BTreeSet<String>> = None;
+            for item in items {
+                if !item.is_object() {
+                    flatten(key.clone(), item, params)?;
+                    continue;
+                }
+                let start = params.len();
+                flatten(key.clone(), item, params)?;
+                let names: BTreeSet<String> = params[start..]
+                    .iter()
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                match &fields {
+                    None => fields = Some(names),
+                    Some(first) if *first != names => {
+                        return Err(CanvasError::Encoding(format!(
+                            "the maps in `{key}` must all have the same fields, \
+                             send the request as JSON instead"
+                        )));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+        Value::Object(map) => {
+            for (field, value) in map {
+                flatten(format!("{key}[{field}]"), value, params)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> Vec<(String, String)> {
+        to_params(&value).unwrap()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn nests_maps_and_arrays() {
+        assert_eq!(
+            params(json!({
+                "course": {"name": "Maths", "term_id": null},
+                "include": ["term", "teachers"],
+                "exclude": [],
+            })),
+            [
+                pair("course[name]", "Maths"),
+                pair("include[]", "term"),
+                pair("include[]", "teachers"),
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_arrays_of_maps_with_the_same_fields() {
+        assert_eq!(
+            params(json!({"grades": [{"user_id": 1, "grade": "A"}, {"grade": "B", "user_id": 2}]})),
+            [
+                pair("grades[][grade]", "A"),
+                pair("grades[][user_id]", "1"),
+                pair("grades[][grade]", "B"),
+                pair("grades[][user_id]", "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_arrays_of_maps_with_different_fields() {
+        for value in [
+            json!({"x": [{"b": 2}, {"a": 1, "b": 3}]}),
+            json!({"x": [{"a": 1, "b": 2}, {"a": 3, "b": null}]}),
+            json!({"x": [{"a": {"c": 1}}, {"a": {"d": 2}}]}),
+        ] {
+            assert!(matches!(to_params(&value), Err(CanvasError::Encoding(_))));
+        }
+    }
+
+    #[test]
+    fn refuses_values_that_are_not_maps() {
+        assert!(matches!(to_params(&[1, 2]), Err(CanvasError::Encoding(_))));
+    }
+}
@@ -1,3 +1,16 @@
+use crate::api::graphql::GraphQLError;
+use crate::link_header::LinkHeaderError;
+use crate::models::progress::Progress;
+use crate::retry::parse_retry_after;
+
+use reqwest::{
+    header::{HeaderMap, WWW_AUTHENTICATE},
+    Method, StatusCode,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,7 +19,400 @@ pub enum CanvasError {
     ParseJSON(#[from] serde_json::Error),
     #[error("Reqwest: {0}")]
     Reqwest(#[from] reqwest::Error),
+    #[error("Invalid configuration: {0}")]
+    Config(#[from] ConfigError),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unable to encode request parameters: {0}")]
+    Encoding(String),
+    #[error("OAuth: {0}")]
+    OAuth(#[from] OAuthError),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(Box<ApiError>),
+    #[error("Forbidden: {0}")]
+    Forbidden(Box<ApiError>),
+    #[error("Not found: {0}")]
+    NotFound(Box<ApiError>),
+    #[error("Throttled: {0}")]
+    Throttled(Box<ApiError>),
+    #[error("Unprocessable entity: {0}")]
+    Unprocessable(Box<ApiError>),
+    #[error("Server error: {0}")]
+    ServerError(Box<ApiError>),
+    #[error("Unexpected status: {0}")]
+    UnexpectedStatus(Box<ApiError>),
+
+    #[error("GraphQL: {}", display_graphql_errors(.0))]
+    GraphQL(Vec<GraphQLError>),
+    #[error("GraphQL response has no {missing} at `{path}`")]
+    GraphQLConnection { path: String, missing: &'static str },
+
+    #[error("Background job {} failed: {}", .0.id, .0.message.as_deref().unwrap_or("no details given"))]
+    JobFailed(Box<Progress>),
+    #[error("Stopped following background job {0} before it finished")]
+    JobUnfinished(u64),
+
+    #[error("Error paginating response: {0}")]
+    Pagination(#[from] LinkHeaderError),
+
+    #[error("Refusing to send a request to `{0}`, which is not on the Canvas instance")]
+    ForeignUrl(String),
+    #[error("Too many redirects, the last one to `{0}`")]
+    TooManyRedirects(String),
+    #[error("Downloaded {received} bytes, but the file has {expected}")]
+    DownloadSize { expected: u64, received: u64 },
+}
+
+/// Errors raised while building a [`Canvas`](crate::Canvas) client.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("no base URL was given")]
+    MissingBaseUrl,
+    #[error("neither an API token nor OAuth credentials were given")]
+    MissingToken,
+    #[error("base URL is empty")]
+    EmptyBaseUrl,
+    #[error("base URL `{0}` has no scheme, expected something like `https://{0}`")]
+    MissingScheme(String),
+    #[error("base URL `{0}` uses plain HTTP, which would send the API token unencrypted")]
+    InsecureScheme(String),
+    #[error("base URL `{0}` has an unsupported scheme, expected `https`")]
+    UnsupportedScheme(String),
+    #[error("base URL `{0}` contains an API path, pass the instance root and set the version with `api_version`")]
+    ContainsApiPath(String),
+    #[error("base URL `{0}` must not contain a query string or fragment")]
+    UnexpectedQuery(String),
+    #[error("base URL `{url}` could not be parsed: {source}")]
+    InvalidUrl {
+        url: String,
+        source: url::ParseError,
+    },
+    #[error("API version must be at least 1")]
+    InvalidApiVersion,
+    #[error("API token may only contain visible ASCII characters")]
+    InvalidToken,
+    #[error("user agent may only contain visible ASCII characters")]
+    InvalidUserAgent,
+    #[cfg(feature = "blocking")]
+    #[error("could not start the runtime of the blocking client: {0}")]
+    Runtime(#[source] std::io::Error),
+}
+
+/// Errors in the OAuth2 flow that are not reported by Canvas itself.
+#[derive(Error, Debug)]
+pub enum OAuthError {
+    #[error("the token store holds no tokens, complete the authorization flow first")]
+    NoTokens,
+    #[error("the access token has expired and there is no refresh token")]
+    NoRefreshToken,
+    #[error("the access token may only contain visible ASCII characters")]
+    InvalidToken,
+}
+
+/// Details about a request that Canvas answered with an error status.
+#[derive(Debug)]
+pub struct ApiError {
+    pub method: Method,
+    pub url: String,
+    pub status: StatusCode,
+    // The messages from the `errors` array (or `message` field) of the response
+    // body. If the body could not be decoded, this holds the raw body instead.
+    pub messages: Vec<String>,
+    // Validation errors per field, as returned from e.g. 400 and 422 responses.
+    pub field_errors: HashMap<String, Vec<String>>,
+    // How long Canvas asked to wait before trying again, from the
+    // `Retry-After` header.
+    pub retry_after: Option<Duration>,
+    // The headers of the response, e.g. to read the remaining rate limit.
+    pub headers: HeaderMap,
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} returned {}", self.method, self.url, self.status)?;
+        let mut details = self.messages.iter().cloned().chain(
+            self.field_errors
+                .iter()
+                .map(|(field, errors)| format!("{field}: {}", errors.join(", "))),
+        );
+        if let Some(first) = details.next() {
+            write!(f, ": {first}")?;
+            for detail in details {
+                write!(f, "; {detail}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The error bodies Canvas returns come in a couple of shapes:
+//  {"errors":[{"message":"..."}]}
+//  {"errors":{"field":[{"attribute":"field","type":"blank","message":"..."}]}}
+//  {"message":"..."}
+//  {"error":"invalid_grant","error_description":"..."} (OAuth2 endpoints)
+#[derive(Deserialize)]
+struct ErrorBody {
+    errors: Option<ErrorList>,
+    message: Option<String>,
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorList {
+    Messages(Vec<ErrorMessage>),
+    Fields(HashMap<String, FieldErrors>),
+    Single(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorMessage {
+    Detailed { message: String },
+    Plain(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FieldErrors {
+    Many(Vec<ErrorMessage>),
+    One(ErrorMessage),
+}
+
+impl From<ErrorMessage> for String {
+    fn from(message: ErrorMessage) -> Self {
+        match message {
+            ErrorMessage::Detailed { message } | ErrorMessage::Plain(message) => message,
+        }
+    }
+}
+
+impl ApiError {
+    fn new(
+        method: Method,
+        url: String,
+        status: StatusCode,
+        retry_after: Option<Duration>,
+        headers: HeaderMap,
+        body: &str,
+    ) -> Self {
+        let mut error = Self {
+            method,
+            url,
+            status,
+            messages: Vec::new(),
+            field_errors: HashMap::new(),
+            retry_after,
+            headers,
+        };
+
+        let Ok(body) = serde_json::from_str::<ErrorBody>(body) else {
+            let body = body.trim();
+            if !body.is_empty() {
+                error.messages.push(body.to_string());
+            }
+            return error;
+        };
+        match body.errors {
+            Some(ErrorList::Messages(messages)) => {
+                error.messages.extend(messages.into_iter().map(Into::into))
+            }
+            Some(ErrorList::Fields(fields)) => {
+                error.field_errors = fields
+                    .into_iter()
+                    .map(|(field, errors)| {
+                        let errors = match errors {
+                            FieldErrors::Many(errors) => {
+                                errors.into_iter().map(Into::into).collect()
+                            }
+                            FieldErrors::One(error) => vec![error.into()],
+                        };
+                        (field, errors)
+                    })
+                    .collect()
+            }
+            Some(ErrorList::Single(message)) => error.messages.push(message),
+            None => {}
+        }
+        error.messages.extend(body.message);
+        error.messages.extend(body.error_description);
+        error
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        self.messages
+            .iter()
+            .any(|message| message.contains("Rate Limit Exceeded"))
+    }
+
+    /// Whether a `401 Unauthorized` means the access token itself was
+    /// rejected, rather than the user lacking permission for the action,
+    /// which Canvas answers with a 401 as well.
+    pub(crate) fn is_invalid_token(&self) -> bool {
+        self.headers.contains_key(WWW_AUTHENTICATE)
+            || self
+                .messages
+                .iter()
+                .any(|message| message.contains("Invalid access token"))
+    }
+}
+
+impl CanvasError {
+    /// Turn an unsuccessful response into the matching error variant.
+    pub(crate) async fn from_response(method: Method, resp: reqwest::Response) -> Self {
+        let url = resp.url().to_string();
+        let status = resp.status();
+        let retry_after = parse_retry_after(resp.headers());
+        let headers = resp.headers().clone();
+        let body = match resp.text().await {
+            Ok(body) => body,
+            Err(e) => return e.into(),
+        };
+        let error = Box::new(ApiError::new(
+            method,
+            url,
+            status,
+            retry_after,
+            headers,
+            &body,
+        ));
+
+        match status {
+            StatusCode::UNAUTHORIZED => CanvasError::Unauthorized(error),
+            StatusCode::TOO_MANY_REQUESTS => CanvasError::Throttled(error),
+            StatusCode::FORBIDDEN if error.is_rate_limited() => CanvasError::Throttled(error),
+            StatusCode::FORBIDDEN => CanvasError::Forbidden(error),
+            StatusCode::NOT_FOUND => CanvasError::NotFound(error),
+            StatusCode::UNPROCESSABLE_ENTITY => CanvasError::Unprocessable(error),
+            s if s.is_server_error() => CanvasError::ServerError(error),
+            _ => CanvasError::UnexpectedStatus(error),
+        }
+    }
+
+    /// The details of the failed request, if this error came from an error
+    /// status returned by Canvas.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            CanvasError::Unauthorized(e)
+            | CanvasError::Forbidden(e)
+            | CanvasError::NotFound(e)
+            | CanvasError::Throttled(e)
+            | CanvasError::Unprocessable(e)
+            | CanvasError::ServerError(e)
+            | CanvasError::UnexpectedStatus(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// The HTTP status code returned by Canvas, if any.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            CanvasError::Reqwest(e) => e.status(),
+            _ => self.api_error().map(|e| e.status),
+        }
+    }
+}
+
+fn display_graphql_errors(errors: &[GraphQLError]) -> String {
+    if errors.is_empty() {
+        return "response contains no data".to_string();
+    }
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn from_response(status: u16, body: &'static str) -> CanvasError {
+        let resp = http::Response::builder().status(status).body(body).unwrap();
+        CanvasError::from_response(Method::GET, resp.into()).await
+    }
+
+    #[tokio::test]
+    async fn errors_array_of_messages() {
+        let error = from_response(
+            404,
+            r#"{"errors":[{"message":"The specified resource does not exist."}]}"#,
+        )
+        .await;
+        let e = error.api_error().unwrap();
+        assert_eq!(e.messages, ["The specified resource does not exist."]);
+        assert!(e.field_errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn errors_map_of_fields() {
+        let error = from_response(
+            400,
+            r#"{"errors":{"name":[{"attribute":"name","type":"blank","message":"can't be blank"}],"sis_course_id":{"message":"is already in use"}}}"#,
+        ).await;
+        let e = error.api_error().unwrap();
+        assert!(e.messages.is_empty());
+        assert_eq!(e.field_errors["name"], ["can't be blank"]);
+        assert_eq!(e.field_errors["sis_course_id"], ["is already in use"]);
+    }
+
+    #[tokio::test]
+    async fn top_level_message() {
+        let error = from_response(500, r#"{"message":"An error occurred."}"#).await;
+        let e = error.api_error().unwrap();
+        assert_eq!(e.messages, ["An error occurred."]);
+    }
+
+    #[tokio::test]
+    async fn oauth_error_description() {
+        let error = from_response(
+            400,
+            r#"{"error":"invalid_grant","error_description":"refresh_token not found"}"#,
+        )
+        .await;
+        let e = error.api_error().unwrap();
+        assert_eq!(e.messages, ["refresh_token not found"]);
+    }
+
+    #[tokio::test]
+    async fn body_that_is_not_json() {
+        let error = from_response(502, "  <html>Bad Gateway</html>\n").await;
+        let e = error.api_error().unwrap();
+        assert_eq!(e.messages, ["<html>Bad Gateway</html>"]);
+        let error = from_response(502, "").await;
+        assert!(error.api_error().unwrap().messages.is_empty());
+    }
 
-    #[error("Error paginating response")]
-    Pagination,
+    #[tokio::test]
+    async fn maps_statuses_to_variants() {
+        let variant = |e: CanvasError| match e {
+            CanvasError::Unauthorized(_) => "unauthorized",
+            CanvasError::Forbidden(_) => "forbidden",
+            CanvasError::NotFound(_) => "not found",
+            CanvasError::Throttled(_) => "throttled",
+            CanvasError::Unprocessable(_) => "unprocessable",
+            CanvasError::ServerError(_) => "server error",
+            CanvasError::UnexpectedStatus(_) => "unexpected",
+            _ => "other",
+        };
+        let cases = [
+            (401, "", "unauthorized"),
+            (403, "403 Forbidden (Rate Limit Exceeded)\n", "throttled"),
+            (403, r#"{"status":"unauthorized"}"#, "forbidden"),
+            (404, "", "not found"),
+            (422, "", "unprocessable"),
+            (429, "", "throttled"),
+            (503, "", "server error"),
+            (409, "", "unexpected"),
+        ];
+        for (status, body, expected) in cases {
+            assert_eq!(
+                variant(from_response(status, body).await),
+                expected,
+                "{status} {body}"
+            );
+        }
+    }
 }
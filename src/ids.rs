@@ -0,0 +1,185 @@
+//! Identifiers for addressing Canvas objects in endpoints.
+//!
+//! Besides their numeric ID, many objects can be addressed by their SIS ID,
+//! their integration ID, or (for users and accounts) as `self`. The numeric
+//! IDs are 64 bit, as global IDs of objects on other shards do not fit in 32.
+//!
+//! All ID types convert from a plain `u64`, so the IDs found in models can be
+//! passed to handlers directly, e.g. `canvas.courses().get(course.id)` as well
+//! as `canvas.courses().get(CourseId::sis("MATH-101"))`.
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+
+// Everything but unreserved characters is escaped, including `.` which Canvas
+// would otherwise take as the start of a format extension.
+const ID_ESCAPE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'~');
+
+/// The different forms an identifier can take.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceId {
+    /// The numeric Canvas ID.
+    Id(u64),
+    /// An ID with a prefix such as `sis_course_id` or `sis_integration_id`,
+    /// e.g. `sis_course_id:MATH-101`.
+    Prefixed(&'static str, String),
+    /// The current user, or the root account of the domain.
+    Current,
+}
+
+impl ResourceId {
+    /// The form used in a URL path, with the value escaped.
+    pub fn to_path_segment(&self) -> String {
+        match self {
+            ResourceId::Id(id) => id.to_string(),
+            ResourceId::Prefixed(prefix, value) => {
+                format!("{prefix}:{}", utf8_percent_encode(value, ID_ESCAPE))
+            }
+            ResourceId::Current => "self".to_string(),
+        }
+    }
+}
+
+impl Display for ResourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceId::Id(id) => write!(f, "{id}"),
+            ResourceId::Prefixed(prefix, value) => write!(f, "{prefix}:{value}"),
+            ResourceId::Current => write!(f, "self"),
+        }
+    }
+}
+
+impl Serialize for ResourceId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ResourceId::Id(id) => serializer.serialize_u64(*id),
+            other => serializer.collect_str(other),
+        }
+    }
+}
+
+/// Deserialize a numeric ID given either as a number or a string, as Canvas
+/// returns large IDs as strings when asked for `application/json+canvas-string-ids`.
+impl<'de> Deserialize<'de> for ResourceId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum NumberOrString {
+            Number(u64),
+            String(String),
+        }
+
+        match NumberOrString::deserialize(deserializer)? {
+            NumberOrString::Number(id) => Ok(ResourceId::Id(id)),
+            NumberOrString::String(s) => s
+                .parse()
+                .map(ResourceId::Id)
+                .map_err(|_| serde::de::Error::custom(format!("invalid numeric ID `{s}`"))),
+        }
+    }
+}
+
+macro_rules! resource_id {
+    ($(#[$meta:meta])* $name:ident, $sis_prefix:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(ResourceId);
+
+        impl $name {
+            pub fn new(id: u64) -> Self {
+                Self(ResourceId::Id(id))
+            }
+
+            #[doc = concat!("Address the object by its SIS ID (`", $sis_prefix, ":...`).")]
+            pub fn sis(id: impl Into<String>) -> Self {
+                Self(ResourceId::Prefixed($sis_prefix, id.into()))
+            }
+
+            /// Address the object by its integration ID (`sis_integration_id:...`).
+            pub fn integration(id: impl Into<String>) -> Self {
+                Self(ResourceId::Prefixed("sis_integration_id", id.into()))
+            }
+
+            /// The numeric ID, if the object is addressed by it.
+            pub fn as_u64(&self) -> Option<u64> {
+                match self.0 {
+                    ResourceId::Id(id) => Some(id),
+                    _ => None,
+                }
+            }
+
+            pub fn as_resource_id(&self) -> &ResourceId {
+                &self.0
+            }
+
+            /// The form used in a URL path, with the value escaped.
+            pub fn to_path_segment(&self) -> String {
+                self.0.to_path_segment()
+            }
+        }
+
+        impl From<u64> for $name {
+            fn from(id: u64) -> Self {
+                Self::new(id)
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+resource_id!(
+    /// Identifies a course.
+    CourseId,
+    "sis_course_id"
+);
+resource_id!(
+    /// Identifies a user.
+    UserId,
+    "sis_user_id"
+);
+resource_id!(
+    /// Identifies an account.
+    AccountId,
+    "sis_account_id"
+);
+resource_id!(
+    /// Identifies a course section.
+    SectionId,
+    "sis_section_id"
+);
+resource_id!(
+    /// Identifies an enrollment term.
+    TermId,
+    "sis_term_id"
+);
+resource_id!(
+    /// Identifies a group.
+    GroupId,
+    "sis_group_id"
+);
+
+impl UserId {
+    /// The user the client is authenticated as.
+    pub fn current() -> Self {
+        Self(ResourceId::Current)
+    }
+
+    /// Address the user by their login (`sis_login_id:...`).
+    pub fn sis_login(login: impl Into<String>) -> Self {
+        Self(ResourceId::Prefixed("sis_login_id", login.into()))
+    }
+}
+
+impl AccountId {
+    /// The root account of the domain the client talks to.
+    pub fn current() -> Self {
+        Self(ResourceId::Current)
+    }
+}
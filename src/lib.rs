@@ -4,79 +4,334 @@
 // TODO: Remove `get_` prefix from functions
 // TODO: Documentation
 // TODO: Testing
-mod api;
+pub mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+pub mod cache;
+pub mod encoding;
 pub mod error;
+pub mod ids;
+pub mod link_header;
+pub mod middleware;
 pub mod models;
+pub mod oauth;
+pub mod pagination;
+pub mod params;
+pub mod rate_limit;
+mod redirect;
+pub mod retry;
 pub mod timestamps;
+#[cfg(feature = "tracing")]
+mod trace;
 
-use futures::Stream;
+use futures::{future::BoxFuture, Stream};
+use std::borrow::Cow;
 use std::pin::Pin;
-pub type PaginatedVec<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+use std::sync::Arc;
+pub type PaginatedVec<T> = Pin<Box<dyn Stream<Item = T> + Send + 'static>>;
 pub type CanvasResult<T> = Result<T, CanvasError>;
 
+pub use builder::CanvasBuilder;
+pub use encoding::RequestBody;
+pub use pagination::{Page, PaginationInfo};
+
+use crate::cache::{CacheStats, HttpCache};
 use crate::error::CanvasError;
-use api::{courses::CourseHandler, users::UserHandler};
+use crate::ids::UserId;
+use crate::middleware::{Middleware, Next};
+use crate::oauth::OAuthSession;
+use crate::pagination::{numbered_page_urls, parse_pagination_info};
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use api::{
+    accounts::AccountHandler, courses::CourseHandler, files::FileHandler, folders::FolderHandler,
+    graphql::GraphQLHandler, progress::ProgressHandler, users::UserHandler,
+};
 
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION, LINK},
+    Method,
+};
 use serde::de::DeserializeOwned;
+use url::{Origin, Url};
 
+/// A Canvas API client.
+///
+/// Cloning is cheap: all clones share the same connection pool, rate limit
+/// budget and configuration.
+#[derive(Clone)]
 pub struct Canvas {
-    client: reqwest::Client,
-    base_url: String,
+    inner: Arc<CanvasInner>,
+    // The user to act as, passed as `as_user_id` with every request.
+    masquerade: Option<Arc<str>>,
+}
+
+pub(crate) struct CanvasInner {
+    pub(crate) client: reqwest::Client,
+    pub(crate) base_url: String,
+    pub(crate) api_version: u32,
+    // Where requests may go, and be sent with credentials.
+    pub(crate) origin: Origin,
+    pub(crate) allow_foreign_urls: bool,
+    // Kept besides the middleware chain to report on them.
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) cache: Option<Arc<HttpCache>>,
+    // Also retries fetches whose body is cut off, which the middleware
+    // cannot see.
+    pub(crate) retry_policy: Arc<RetryPolicy>,
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+    pub(crate) per_page: Option<u32>,
+    pub(crate) page_concurrency: usize,
+}
+
+impl std::fmt::Debug for Canvas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Canvas")
+            .field("base_url", &self.inner.base_url)
+            .field("api_version", &self.inner.api_version)
+            // The credentials live in the middleware chain and are never
+            // printed.
+            .field("rate_limited", &self.inner.rate_limiter.is_some())
+            .field("cached", &self.inner.cache.is_some())
+            .field("masquerade", &self.masquerade)
+            .finish_non_exhaustive()
+    }
 }
 
-#[derive(Debug, Default)]
-pub struct PaginationInfo {
-    current_url: Option<String>,
-    next_url: Option<String>,
-    prev_url: Option<String>,
-    first_url: Option<String>,
-    last_url: Option<String>,
+/// How requests are authenticated.
+pub(crate) enum Credentials {
+    /// A static access token, as the value of the `Authorization` header.
+    Token(HeaderValue),
+    OAuth(OAuthSession),
 }
 
+/// Adds the credentials to requests, as long as they go to the Canvas
+/// instance.
+pub(crate) struct Authenticator {
+    pub(crate) credentials: Credentials,
+    pub(crate) origin: Origin,
+}
+
+impl Middleware for Authenticator {
+    /// Add the `Authorization` header. When authenticated through OAuth2 and
+    /// Canvas rejects the access token, the token is refreshed and the request
+    /// sent once more. Other 401s, e.g. for missing permissions, are returned
+    /// as they are.
+    fn handle<'a>(
+        &'a self,
+        mut req: reqwest::Request,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, CanvasResult<reqwest::Response>> {
+        Box::pin(async move {
+            // Checked again here, as middleware may have changed the URL.
+            if req.url().origin() != self.origin {
+                return next.run(req).await;
+            }
+
+            let session = match &self.credentials {
+                Credentials::Token(authorization) => {
+                    req.headers_mut()
+                        .insert(AUTHORIZATION, authorization.clone());
+                    return next.run(req).await;
+                }
+                Credentials::OAuth(session) => session,
+            };
+
+            let access_token = session.access_token().await?;
+            req.headers_mut()
+                .insert(AUTHORIZATION, oauth::bearer(&access_token)?);
+            let Some(mut retry) = req.try_clone() else {
+                return next.run(req).await;
+            };
+            match next.run(req).await {
+                Err(CanvasError::Unauthorized(e)) if e.is_invalid_token() => {
+                    let tokens = session.refresh(&access_token).await?;
+                    retry
+                        .headers_mut()
+                        .insert(AUTHORIZATION, oauth::bearer(&tokens.access_token)?);
+                    next.run(retry).await
+                }
+                result => result,
+            }
+        })
+    }
+}
+
+/// Pass successful responses through and turn everything else into the
+/// matching [`CanvasError`].
+pub(crate) async fn check_status(
+    method: Method,
+    resp: reqwest::Response,
+) -> CanvasResult<reqwest::Response> {
+    if resp.status().is_success() {
+        Ok(resp)
+    } else {
+        Err(CanvasError::from_response(method, resp).await)
+    }
+}
+
+// The body is read before decoding it, so that a reqwest decode error always
+// means the body was cut off, which is retried, and never invalid JSON.
 async fn convert_response<R: DeserializeOwned>(resp: reqwest::Response) -> CanvasResult<R> {
-    resp.json::<R>().await.map_err(Into::into)
+    let body = resp.bytes().await?;
+    serde_json::from_slice(&body).map_err(Into::into)
 }
 
 impl Canvas {
-    // TODO: Include API version as an argument
+    /// Create a client with the default settings.
+    ///
+    /// Use [`Canvas::builder`] to pick the API version, timeouts, proxies etc.
     pub fn init(base_url: &str, api_token: &str) -> CanvasResult<Self> {
-        // TODO: Warn/error if URL...
-        // - contains an API version
-        // - contains "http://"
-        // - does not contain "://"
-        // - is empty
-        // See the `Canvas` object at https://github.com/ucfopen/canvasapi/ for more info
-        Ok(Self {
-            client: reqwest::Client::builder()
-                .default_headers({
-                    let mut headers = HeaderMap::new();
-                    headers.insert(
-                        AUTHORIZATION,
-                        HeaderValue::from_str(&format!("Bearer {}", api_token))
-                            .expect("Token must consist of only visible ASCII charcters"),
-                    );
-                    headers
-                })
-                .build()?,
-            base_url: base_url.to_string(),
-        })
+        Self::builder().base_url(base_url).token(api_token).build()
+    }
+
+    pub fn builder() -> CanvasBuilder {
+        CanvasBuilder::new()
+    }
+
+    /// A view of this client that acts as another user, seeing and doing only
+    /// what that user could. Requires the "Become other users" permission.
+    ///
+    /// Every request made through the returned client, including those of
+    /// handlers and the page requests of streams, carries the masquerade.
+    pub fn as_user(&self, user_id: impl Into<UserId>) -> Canvas {
+        Canvas {
+            inner: self.inner.clone(),
+            masquerade: Some(user_id.into().to_string().into()),
+        }
+    }
+
+    /// The user this client is acting as, if any.
+    pub fn masquerading_as(&self) -> Option<&str> {
+        self.masquerade.as_deref()
+    }
+
+    /// Add the masquerade parameter to `url`, unless it already carries one
+    /// (as the pagination links Canvas returns usually do).
+    pub(crate) fn masqueraded_url<'a>(&self, url: &'a str) -> Cow<'a, str> {
+        let Some(user_id) = &self.masquerade else {
+            return Cow::Borrowed(url);
+        };
+        let Ok(mut parsed) = Url::parse(url) else {
+            return Cow::Borrowed(url);
+        };
+        if parsed.query_pairs().any(|(key, _)| key == "as_user_id") {
+            return Cow::Borrowed(url);
+        }
+        parsed.query_pairs_mut().append_pair("as_user_id", user_id);
+        Cow::Owned(parsed.into())
+    }
+
+    /// The underlying HTTP client, for requests that must not go through the
+    /// middleware chain.
+    pub(crate) fn http(&self) -> &reqwest::Client {
+        &self.inner.client
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.inner.base_url
     }
 
     fn url_from_endpoint(&self, endpoint: &str) -> String {
-        format!("{}/api/v1/{}", self.base_url, endpoint)
+        format!(
+            "{}/api/v{}/{}",
+            self.inner.base_url, self.inner.api_version, endpoint
+        )
     }
 
-    pub async fn get(
+    fn url_with_params(&self, endpoint: &str, params: &[(String, String)]) -> String {
+        let url = self.url_from_endpoint(endpoint);
+        if params.is_empty() {
+            return url;
+        }
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .finish();
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{separator}{query}")
+    }
+
+    /// Send a request through the middleware chain.
+    ///
+    /// URLs outside the Canvas instance are rejected, unless
+    /// [`CanvasBuilder::allow_foreign_urls`] is set.
+    async fn send(
         &self,
+        method: Method,
         url: &str,
         headers: Option<HeaderMap>,
+        body: &RequestBody,
     ) -> CanvasResult<reqwest::Response> {
-        let mut req = self.client.get(url);
+        // Masquerading only applies to the instance, other hosts would just
+        // learn the user ID.
+        let url = match Url::parse(url) {
+            Ok(parsed) if self.is_same_origin(&parsed) => self.masqueraded_url(url),
+            _ => Cow::Borrowed(url),
+        };
+        let mut req = body.apply(self.inner.client.request(method, url.as_ref()));
         if let Some(headers) = headers {
             req = req.headers(headers);
         }
-        req.send().await.map_err(Into::into)
+        let req = req.build()?;
+        if !self.is_same_origin(req.url()) && !self.inner.allow_foreign_urls {
+            return Err(CanvasError::ForeignUrl(req.url().to_string()));
+        }
+        self.execute(req).await
+    }
+
+    /// Run `req` through the middleware chain, wherever it points to.
+    pub(crate) async fn execute(&self, req: reqwest::Request) -> CanvasResult<reqwest::Response> {
+        Next::new(&self.inner.client, &self.inner.middleware, false)
+            .run(req)
+            .await
+    }
+
+    /// Like [`execute`](Self::execute), but past the response cache, for
+    /// responses that are streamed rather than read whole.
+    pub(crate) async fn execute_uncached(
+        &self,
+        req: reqwest::Request,
+    ) -> CanvasResult<reqwest::Response> {
+        Next::new(&self.inner.client, &self.inner.middleware, true)
+            .run(req)
+            .await
+    }
+
+    pub(crate) fn is_same_origin(&self, url: &Url) -> bool {
+        url.origin() == self.inner.origin
+    }
+
+    /// Send a `GET` request to an absolute URL, e.g. a link returned by the
+    /// API.
+    ///
+    /// The URL has to point at the Canvas instance, unless
+    /// [`CanvasBuilder::allow_foreign_urls`] is set; even then, the token is
+    /// only ever sent to the instance itself.
+    pub async fn get(
+        &self,
+        url: &str,
+        headers: Option<HeaderMap>,
+    ) -> CanvasResult<reqwest::Response> {
+        self.send(Method::GET, url, headers, &RequestBody::Empty)
+            .await
+    }
+
+    /// The rate limit budget the client estimates to be left for its token.
+    ///
+    /// Returns `None` if client side throttling is disabled.
+    pub fn rate_limit_remaining(&self) -> Option<f64> {
+        self.inner
+            .rate_limiter
+            .as_deref()
+            .map(RateLimiter::remaining)
+    }
+
+    /// How many `GET` requests were served from the response cache so far.
+    ///
+    /// Returns `None` if no cache is configured.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.inner.cache.as_deref().map(HttpCache::stats)
     }
 
     pub async fn get_endpoint<R: DeserializeOwned>(
@@ -84,73 +339,504 @@ impl Canvas {
         endpoint: &str,
         headers: Option<HeaderMap>,
     ) -> CanvasResult<R> {
-        let resp = self.get(&self.url_from_endpoint(endpoint), headers).await?;
+        self.get_url(&self.url_from_endpoint(endpoint), headers)
+            .await
+    }
+
+    /// Send a `GET` request to an absolute URL and decode the response,
+    /// fetching it again if its body is cut off.
+    pub(crate) async fn get_url<R: DeserializeOwned>(
+        &self,
+        url: &str,
+        headers: Option<HeaderMap>,
+    ) -> CanvasResult<R> {
+        self.inner
+            .retry_policy
+            .retry_body(|| async {
+                let resp = self
+                    .send(Method::GET, url, headers.clone(), &RequestBody::Empty)
+                    .await?;
+                convert_response(resp).await
+            })
+            .await
+    }
+
+    /// Send a request with a body to an endpoint and decode the response.
+    ///
+    /// Only idempotent methods (`PUT`, `DELETE`, ...) are retried.
+    pub async fn request_endpoint<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_url(method, &self.url_from_endpoint(endpoint), body)
+            .await
+    }
+
+    pub(crate) async fn request_url<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        let resp = self.send(method, url, None, &body).await?;
         convert_response(resp).await
     }
 
-    fn parse_pagination_info(link_header: Option<&HeaderValue>) -> CanvasResult<PaginationInfo> {
-        let mut info: PaginationInfo = Default::default();
-        let Some(links) = link_header else {
-            return Ok(info);
-        };
+    pub async fn post_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_endpoint(Method::POST, endpoint, body).await
+    }
 
-        let Ok(links) = links.to_str() else {
-            return Err(CanvasError::Pagination);
-        };
+    pub async fn put_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_endpoint(Method::PUT, endpoint, body).await
+    }
 
-        for link in links.split(',') {
-            let (link, mut rel) = link.split_once(';').ok_or(CanvasError::Pagination)?;
-            rel = rel.strip_prefix(" rel=\"").ok_or(CanvasError::Pagination)?;
-            rel = rel.strip_suffix('\"').ok_or(CanvasError::Pagination)?;
-            let mut link = link.chars();
-            link.next();
-            link.next_back();
-            let link = link.as_str();
-            match rel {
-                "current" => info.current_url = Some(link.to_string()),
-                "prev" => info.prev_url = Some(link.to_string()),
-                "next" => info.next_url = Some(link.to_string()),
-                "first" => info.first_url = Some(link.to_string()),
-                "last" => info.last_url = Some(link.to_string()),
-                _ => { /* warn, unknown rel value */ }
-            }
-        }
+    pub async fn delete_endpoint<R: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: RequestBody,
+    ) -> CanvasResult<R> {
+        self.request_endpoint(Method::DELETE, endpoint, body).await
+    }
 
-        Ok(info)
+    pub async fn stream_endpoint<T: DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &str,
+    ) -> PaginatedVec<CanvasResult<T>> {
+        self.stream_endpoint_with_params(endpoint, &[]).await
     }
 
-    pub async fn stream_endpoint<'a, T: DeserializeOwned + 'a>(
-        &'a self,
+    /// Stream all items of a paginated endpoint, passing `params` in the query
+    /// string of the first request. Canvas carries them over into the links to
+    /// the following pages.
+    pub async fn stream_endpoint_with_params<T: DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &str,
+        params: &[(String, String)],
+    ) -> PaginatedVec<CanvasResult<T>> {
+        let pages = self.stream_pages_with_params(endpoint, params).await;
+        Self::flatten_pages(pages)
+    }
+
+    /// Stream all items starting at the page behind `url`, e.g. a
+    /// [`PaginationInfo::next_url`] saved from an earlier run.
+    pub async fn stream_from_url<T: DeserializeOwned + Send + 'static>(
+        &self,
+        url: &str,
+    ) -> PaginatedVec<CanvasResult<T>> {
+        let pages = self.stream_pages_from_url(url).await;
+        Self::flatten_pages(pages)
+    }
+
+    /// Stream a paginated endpoint page by page, along with the pagination
+    /// links of every page.
+    pub async fn stream_pages<T: DeserializeOwned + Send + 'static>(
+        &self,
+        endpoint: &str,
+    ) -> PaginatedVec<CanvasResult<Page<T>>> {
+        self.stream_pages_with_params(endpoint, &[]).await
+    }
+
+    pub async fn stream_pages_with_params<T: DeserializeOwned + Send + 'static>(
+        &self,
         endpoint: &str,
-    ) -> PaginatedVec<'_, CanvasResult<T>> {
+        params: &[(String, String)],
+    ) -> PaginatedVec<CanvasResult<Page<T>>> {
+        let mut params = params.to_vec();
+        if let Some(per_page) = self.inner.per_page {
+            if !params.iter().any(|(key, _)| key == "per_page") {
+                params.push(("per_page".to_string(), per_page.to_string()));
+            }
+        }
+        self.stream_pages_from_url(&self.url_with_params(endpoint, &params))
+            .await
+    }
+
+    /// Stream page by page, starting at the page behind `url`.
+    ///
+    /// If Canvas numbers the pages of the endpoint, the remaining pages are
+    /// fetched concurrently (see [`CanvasBuilder::page_concurrency`]) once the
+    /// first one has told how many there are. They are still yielded in order.
+    pub async fn stream_pages_from_url<T: DeserializeOwned + Send + 'static>(
+        &self,
+        url: &str,
+    ) -> PaginatedVec<CanvasResult<Page<T>>> {
         use async_stream::stream;
-        let mut first_url = Some(self.url_from_endpoint(endpoint));
+        use futures::StreamExt;
+        let canvas = self.clone();
+        let mut next_url = Some(url.to_string());
 
         Box::pin(stream! {
-            loop {
-                let Some(url) = first_url else {
-                    break;
+            while let Some(url) = next_url {
+                let page = canvas.get_page::<T>(&url).await?;
+                next_url = page.pagination.next_url.clone();
+                let prefetch = if canvas.inner.page_concurrency > 1 {
+                    numbered_page_urls(&page.pagination)
+                } else {
+                    None
                 };
-                let resp = self.get(&url, None).await?;
-                let pag_info = Canvas::parse_pagination_info(resp.headers().get("link"))?;
+                yield Ok(page);
 
-                let items = resp.json::<Vec<T>>().await?;
-                for item in items {
-                    yield Ok(item);
+                if let Some(urls) = prefetch {
+                    let mut pages = futures::stream::iter(urls)
+                        .map(|url| {
+                            let canvas = &canvas;
+                            async move { canvas.get_page::<T>(&url).await }
+                        })
+                        .buffered(canvas.inner.page_concurrency);
+                    while let Some(page) = pages.next().await {
+                        let page = page?;
+                        // Only the link of the last page matters, in case more
+                        // items were added while paginating.
+                        next_url = page.pagination.next_url.clone();
+                        yield Ok(page);
+                    }
                 }
+            }
+        })
+    }
+
+    /// Fetch and decode a single page.
+    async fn get_page<T: DeserializeOwned>(&self, url: &str) -> CanvasResult<Page<T>> {
+        let fetch = async {
+            let (items, pagination) = self
+                .inner
+                .retry_policy
+                .retry_body(|| async {
+                    let resp = self
+                        .send(Method::GET, url, None, &RequestBody::Empty)
+                        .await?;
+                    let pagination = parse_pagination_info(resp.headers().get(LINK), resp.url())?;
+                    Ok((convert_response::<Vec<T>>(resp).await?, pagination))
+                })
+                .await?;
+            #[cfg(feature = "tracing")]
+            tracing::Span::current()
+                .record("items", items.len())
+                .record("has_next", pagination.has_next());
+            Ok(Page { items, pagination })
+        };
+        #[cfg(feature = "tracing")]
+        let fetch = tracing::Instrument::instrument(fetch, trace::page_span(url));
+        fetch.await
+    }
 
-                first_url = pag_info.next_url;
+    pub(crate) fn flatten_pages<T: Send + 'static>(
+        mut pages: PaginatedVec<CanvasResult<Page<T>>>,
+    ) -> PaginatedVec<CanvasResult<T>> {
+        use async_stream::stream;
+        use futures::StreamExt;
+
+        Box::pin(stream! {
+            while let Some(page) = pages.next().await {
+                for item in page?.items {
+                    yield Ok(item);
+                }
             }
         })
     }
 }
 
 impl Canvas {
+    pub fn accounts(&self) -> AccountHandler {
+        AccountHandler::new(self.clone())
+    }
+
     pub fn courses(&self) -> CourseHandler {
-        CourseHandler::new(self)
+        CourseHandler::new(self.clone())
     }
 
     pub fn users(&self) -> UserHandler {
-        UserHandler::new(self)
+        UserHandler::new(self.clone())
+    }
+
+    pub fn files(&self) -> FileHandler {
+        FileHandler::new(self.clone())
+    }
+
+    pub fn folders(&self) -> FolderHandler {
+        FolderHandler::new(self.clone())
+    }
+
+    pub fn graphql(&self) -> GraphQLHandler {
+        GraphQLHandler::new(self.clone())
+    }
+
+    pub fn progress(&self) -> ProgressHandler {
+        ProgressHandler::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oauth::{MemoryTokenStore, OAuthClient, OAuthConfig, TokenSet};
+    use serde_json::Value;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    /// Serve `respond(request line, headers)` on a local port, recording the
+    /// request lines in the order they arrive. Each connection is handled on
+    /// its own thread.
+    pub(crate) fn serve(
+        respond: impl Fn(&str, &HeaderMap) -> String + Send + Sync + 'static,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        let respond = Arc::new(respond);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let (log, respond) = (log.clone(), respond.clone());
+                std::thread::spawn(move || {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let (mut request_line, mut headers) = (String::new(), HeaderMap::new());
+                    reader.read_line(&mut request_line).unwrap();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        if line.trim().is_empty() {
+                            break;
+                        }
+                        let (name, value) = line.split_once(':').unwrap();
+                        headers.append(
+                            reqwest::header::HeaderName::from_bytes(name.as_bytes()).unwrap(),
+                            value.trim().parse().unwrap(),
+                        );
+                    }
+                    let length = headers
+                        .get(reqwest::header::CONTENT_LENGTH)
+                        .map_or(0, |length| length.to_str().unwrap().parse().unwrap());
+                    reader.read_exact(&mut vec![0; length]).unwrap();
+                    let request_line = request_line.trim().to_string();
+                    log.lock().unwrap().push(request_line.clone());
+                    let response = respond(&request_line, &headers);
+                    stream.write_all(response.as_bytes()).unwrap();
+                });
+            }
+        });
+        (base_url, requests)
+    }
+
+    pub(crate) fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\n{headers}content-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn oauth_canvas(base_url: &str) -> Canvas {
+        let config = OAuthConfig {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "https://app.example.com/callback".to_string(),
+        };
+        let store = MemoryTokenStore::new(TokenSet {
+            access_token: "old".to_string(),
+            refresh_token: Some("refresh".to_string()),
+            expires_at: None,
+            user: None,
+        });
+        Canvas::builder()
+            .base_url(base_url)
+            .allow_http(true)
+            .oauth(OAuthClient::new(base_url, config).unwrap(), Arc::new(store))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn permission_401_is_not_refreshed() {
+        let (base_url, requests) = serve(|_, _| {
+            response(
+                "401 Unauthorized",
+                "",
+                r#"{"status":"unauthorized","errors":[{"message":"user not authorized to perform that action"}]}"#,
+            )
+        });
+        let canvas = oauth_canvas(&base_url);
+
+        let result = canvas.get_endpoint::<Value>("courses/1", None).await;
+        let Err(CanvasError::Unauthorized(error)) = result else {
+            panic!("expected Unauthorized, got {result:?}");
+        };
+        assert_eq!(
+            error.messages,
+            ["user not authorized to perform that action"]
+        );
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["GET /api/v1/courses/1 HTTP/1.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_token_401_is_refreshed_once() {
+        let (base_url, requests) = serve(|request_line, headers| {
+            if request_line.starts_with("POST /login/oauth2/token") {
+                response("200 OK", "", r#"{"access_token":"new","expires_in":3600}"#)
+            } else if headers[AUTHORIZATION] == "Bearer new" {
+                response("200 OK", "", "{}")
+            } else {
+                response(
+                    "401 Unauthorized",
+                    "www-authenticate: Bearer realm=\"canvas-lms\"\r\n",
+                    r#"{"errors":[{"message":"Invalid access token."}]}"#,
+                )
+            }
+        });
+        let canvas = oauth_canvas(&base_url);
+
+        canvas
+            .get_endpoint::<Value>("courses/1", None)
+            .await
+            .unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "GET /api/v1/courses/1 HTTP/1.1",
+                "POST /login/oauth2/token HTTP/1.1",
+                "GET /api/v1/courses/1 HTTP/1.1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn truncated_body_is_fetched_again() {
+        let count = Mutex::new(0);
+        let (base_url, requests) = serve(move |_, _| {
+            let mut count = count.lock().unwrap();
+            *count += 1;
+            if *count % 2 == 1 {
+                // Promises more than it sends before closing the connection.
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 100\r\nconnection: close\r\n\r\n[{\"id\"".to_string()
+            } else {
+                response("200 OK", "", r#"[{"id":1}]"#)
+            }
+        });
+        let canvas = Canvas::builder()
+            .base_url(&base_url)
+            .allow_http(true)
+            .token("token")
+            .retry_policy(RetryPolicy {
+                initial_backoff: std::time::Duration::ZERO,
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+
+        let items: Vec<Value> = canvas.get_endpoint("courses", None).await.unwrap();
+        assert_eq!(items.len(), 1);
+        let page = canvas
+            .get_page::<Value>(&format!("{base_url}/api/v1/courses"))
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(requests.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn downloads_bypass_the_response_cache() {
+        use crate::cache::{MemoryCache, ResponseCache};
+
+        let (base_url, _) = serve(|_, _| response("200 OK", "etag: \"v1\"\r\n", r#"{"a":1}"#));
+        let cache = Arc::new(MemoryCache::new(10));
+        let canvas = Canvas::builder()
+            .base_url(&base_url)
+            .allow_http(true)
+            .token("token")
+            .response_cache(cache.clone())
+            .build()
+            .unwrap();
+
+        let url = format!("{base_url}/files/1/download");
+        let mut written = Vec::new();
+        canvas.download(&url).to_writer(&mut written).await.unwrap();
+        assert_eq!(written, br#"{"a":1}"#);
+        assert!(cache.get(&url).await.is_none());
+        assert_eq!(canvas.cache_stats(), Some(Default::default()));
+    }
+
+    #[tokio::test]
+    async fn masquerade_stays_on_the_instance() {
+        let (base_url, requests) = serve(|_, _| response("200 OK", "", "{}"));
+        let (foreign_url, foreign_requests) = serve(|_, _| response("200 OK", "", "{}"));
+        let canvas = Canvas::builder()
+            .base_url(&base_url)
+            .allow_http(true)
+            .allow_foreign_urls(true)
+            .token("token")
+            .build()
+            .unwrap()
+            .as_user(5);
+
+        canvas.get(&format!("{base_url}/a"), None).await.unwrap();
+        canvas.get(&format!("{foreign_url}/b"), None).await.unwrap();
+        assert_eq!(*requests.lock().unwrap(), ["GET /a?as_user_id=5 HTTP/1.1"]);
+        assert_eq!(*foreign_requests.lock().unwrap(), ["GET /b HTTP/1.1"]);
+    }
+
+    #[tokio::test]
+    async fn prefetched_pages_keep_their_order() {
+        use futures::StreamExt;
+
+        let (base_url, requests) = serve(|request_line, headers| {
+            let page: u64 = request_line
+                .split("page=")
+                .nth(1)
+                .and_then(|rest| rest.split(' ').next())
+                .unwrap()
+                .parse()
+                .unwrap();
+            // Later pages answer first.
+            std::thread::sleep(std::time::Duration::from_millis((5 - page) * 40));
+            let url = |page| {
+                format!(
+                    "http://{}/api/v1/items?page={page}",
+                    headers["host"].to_str().unwrap()
+                )
+            };
+            let mut link = format!("<{}>; rel=\"last\"", url(5));
+            if page < 5 {
+                link = format!("<{}>; rel=\"next\", {link}", url(page + 1));
+            }
+            response("200 OK", &format!("link: {link}\r\n"), &format!("[{page}]"))
+        });
+        let canvas = Canvas::builder()
+            .base_url(&base_url)
+            .allow_http(true)
+            .token("token")
+            .page_concurrency(4)
+            .build()
+            .unwrap();
+
+        let pages: Vec<_> = canvas
+            .stream_pages_from_url::<u64>(&format!("{base_url}/api/v1/items?page=1"))
+            .await
+            .map(|page| page.unwrap().items)
+            .collect()
+            .await;
+        assert_eq!(pages, [[1], [2], [3], [4], [5]]);
+
+        let mut requested = requests.lock().unwrap().clone();
+        requested.sort();
+        assert_eq!(
+            requested,
+            (1..=5)
+                .map(|page| format!("GET /api/v1/items?page={page} HTTP/1.1"))
+                .collect::<Vec<_>>()
+        );
     }
 }
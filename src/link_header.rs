@@ -0,0 +1,411 @@
+//! Parser for the `Link` header as specified in
+//! [RFC 8288](https://www.rfc-editor.org/rfc/rfc8288#section-3).
+//!
+//! ```text
+//! Link       = #link-value
+//! link-value = "<" URI-Reference ">" *( OWS ";" OWS link-param )
+//! link-param = token BWS [ "=" BWS ( token / quoted-string ) ]
+//! ```
+use std::fmt::Display;
+use thiserror::Error;
+
+/// A single link of a `Link` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// The target URI, exactly as given between the angle brackets.
+    pub uri: String,
+    /// The link parameters in the order they appeared. Names are lowercased,
+    /// as they are case-insensitive. Quoted values are unescaped.
+    pub params: Vec<(String, Option<String>)>,
+}
+
+impl Link {
+    /// The value of the first parameter called `name`. Later occurrences are
+    /// ignored, as the RFC requires for `rel`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// The relation types of the link. A single `rel` parameter can hold
+    /// several of them, separated by whitespace.
+    pub fn rels(&self) -> impl Iterator<Item = &str> {
+        self.param("rel")
+            .unwrap_or_default()
+            .split_ascii_whitespace()
+    }
+
+    /// Whether the link has the relation type `rel` (compared
+    /// case-insensitively).
+    pub fn has_rel(&self, rel: &str) -> bool {
+        self.rels().any(|r| r.eq_ignore_ascii_case(rel))
+    }
+}
+
+/// A malformed `Link` header.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("malformed link #{link} at byte {position}: {kind}")]
+pub struct LinkHeaderError {
+    /// Index of the offending link within the header, starting at 0.
+    pub link: usize,
+    /// Byte offset into the header where parsing failed.
+    pub position: usize,
+    pub kind: LinkHeaderErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkHeaderErrorKind {
+    /// The header contains bytes that are not visible ASCII.
+    InvalidEncoding,
+    /// A link does not start with `<`.
+    ExpectedUri,
+    /// The closing `>` of a URI is missing.
+    UnterminatedUri,
+    /// A parameter name is missing or contains invalid characters.
+    ExpectedParamName,
+    /// A `=` is not followed by a token or a quoted string.
+    ExpectedParamValue,
+    /// The closing `"` of a quoted string is missing.
+    UnterminatedQuotedString,
+    /// Something other than `;` or `,` follows a link or parameter.
+    UnexpectedCharacter(char),
+}
+
+impl Display for LinkHeaderErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidEncoding => write!(f, "header is not visible ASCII"),
+            Self::ExpectedUri => write!(f, "expected `<` starting a URI"),
+            Self::UnterminatedUri => write!(f, "URI is missing its closing `>`"),
+            Self::ExpectedParamName => write!(f, "expected a parameter name"),
+            Self::ExpectedParamValue => write!(f, "expected a token or quoted string after `=`"),
+            Self::UnterminatedQuotedString => {
+                write!(f, "quoted string is missing its closing `\"`")
+            }
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`"),
+        }
+    }
+}
+
+/// Parse the value of a `Link` header into its links.
+pub fn parse(header: &str) -> Result<Vec<Link>, LinkHeaderError> {
+    Parser {
+        input: header.as_bytes(),
+        position: 0,
+        link: 0,
+    }
+    .parse()
+}
+
+/// Like [`parse`], for a raw header value.
+pub fn parse_header_value(
+    header: &reqwest::header::HeaderValue,
+) -> Result<Vec<Link>, LinkHeaderError> {
+    let header = header.to_str().map_err(|_| LinkHeaderError {
+        link: 0,
+        position: 0,
+        kind: LinkHeaderErrorKind::InvalidEncoding,
+    })?;
+    parse(header)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    link: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Vec<Link>, LinkHeaderError> {
+        let mut links = Vec::new();
+        loop {
+            // The list syntax allows empty elements, e.g. `<a>, , <b>`.
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some(b',') => {
+                    self.position += 1;
+                    continue;
+                }
+                Some(_) => {}
+            }
+
+            links.push(self.link_value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                None => break,
+                Some(b',') => self.position += 1,
+                Some(c) => {
+                    return Err(self.error(LinkHeaderErrorKind::UnexpectedCharacter(c as char)))
+                }
+            }
+            self.link += 1;
+        }
+        Ok(links)
+    }
+
+    fn link_value(&mut self) -> Result<Link, LinkHeaderError> {
+        if self.peek() != Some(b'<') {
+            return Err(self.error(LinkHeaderErrorKind::ExpectedUri));
+        }
+        self.position += 1;
+        let start = self.position;
+        let Some(length) = self.input[start..].iter().position(|&c| c == b'>') else {
+            return Err(self.error(LinkHeaderErrorKind::UnterminatedUri));
+        };
+        let uri = self.text(start, start + length).trim().to_string();
+        self.position = start + length + 1;
+
+        let mut params = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b';') {
+                break;
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            // Tolerate a trailing or doubled `;`.
+            if matches!(self.peek(), None | Some(b',') | Some(b';')) {
+                continue;
+            }
+            params.push(self.link_param()?);
+        }
+
+        Ok(Link { uri, params })
+    }
+
+    fn link_param(&mut self) -> Result<(String, Option<String>), LinkHeaderError> {
+        let name = self.token();
+        if name.is_empty() {
+            return Err(self.error(LinkHeaderErrorKind::ExpectedParamName));
+        }
+        let name = name.to_ascii_lowercase();
+
+        self.skip_whitespace();
+        if self.peek() != Some(b'=') {
+            return Ok((name, None));
+        }
+        self.position += 1;
+        self.skip_whitespace();
+
+        let value = if self.peek() == Some(b'"') {
+            self.quoted_string()?
+        } else {
+            let token = self.token();
+            if token.is_empty() {
+                return Err(self.error(LinkHeaderErrorKind::ExpectedParamValue));
+            }
+            token.to_string()
+        };
+        Ok((name, Some(value)))
+    }
+
+    fn quoted_string(&mut self) -> Result<String, LinkHeaderError> {
+        let start = self.position;
+        self.position += 1;
+        let mut value = Vec::new();
+        while let Some(c) = self.peek() {
+            self.position += 1;
+            match c {
+                b'"' => return Ok(String::from_utf8_lossy(&value).into_owned()),
+                b'\\' => match self.peek() {
+                    Some(escaped) => {
+                        value.push(escaped);
+                        self.position += 1;
+                    }
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        self.position = start;
+        Err(self.error(LinkHeaderErrorKind::UnterminatedQuotedString))
+    }
+
+    fn token(&mut self) -> &str {
+        let start = self.position;
+        while self.peek().is_some_and(is_token_char) {
+            self.position += 1;
+        }
+        self.text(start, self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t')) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.position).copied()
+    }
+
+    fn text(&self, start: usize, end: usize) -> &str {
+        // The input came from a `&str` and the boundaries are ASCII bytes.
+        std::str::from_utf8(&self.input[start..end]).unwrap_or_default()
+    }
+
+    fn error(&self, kind: LinkHeaderErrorKind) -> LinkHeaderError {
+        LinkHeaderError {
+            link: self.link,
+            position: self.position,
+            kind,
+        }
+    }
+}
+
+// tchar as defined in RFC 9110, section 5.6.2.
+fn is_token_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn link(uri: &str, params: &[(&str, Option<&str>)]) -> Link {
+        Link {
+            uri: uri.to_string(),
+            params: params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.map(str::to_string)))
+                .collect(),
+        }
+    }
+
+    fn error(header: &str) -> LinkHeaderError {
+        parse(header).unwrap_err()
+    }
+
+    #[test]
+    fn commas_inside_uris() {
+        assert_eq!(
+            parse(r#"<https://x.test/a?ids=1,2>; rel="next", <https://x.test/b>; rel="last""#)
+                .unwrap(),
+            [
+                link("https://x.test/a?ids=1,2", &[("rel", Some("next"))]),
+                link("https://x.test/b", &[("rel", Some("last"))]),
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_params() {
+        let links = parse(
+            r#"<https://x.test/a>; rel="next"; title="Page 2"; type="text/html"; crossorigin"#,
+        )
+        .unwrap();
+        assert_eq!(
+            links,
+            [link(
+                "https://x.test/a",
+                &[
+                    ("rel", Some("next")),
+                    ("title", Some("Page 2")),
+                    ("type", Some("text/html")),
+                    ("crossorigin", None),
+                ]
+            )]
+        );
+        assert_eq!(links[0].param("TITLE"), Some("Page 2"));
+        assert_eq!(links[0].param("crossorigin"), None);
+    }
+
+    #[test]
+    fn first_rel_param_wins() {
+        let links = parse("<a>; rel=next; rel=last").unwrap();
+        assert!(links[0].has_rel("next"));
+        assert!(!links[0].has_rel("last"));
+    }
+
+    #[test]
+    fn multiple_rels() {
+        let links = parse(r#"<a>; rel="current  first""#).unwrap();
+        assert_eq!(links[0].rels().collect::<Vec<_>>(), ["current", "first"]);
+        assert!(links[0].has_rel("FIRST"));
+        assert!(!links[0].has_rel("next"));
+    }
+
+    #[test]
+    fn whitespace_and_empty_elements() {
+        assert_eq!(
+            parse("  < a > ;REL = next ;, ,\t<b>;;rel=\"prev\"  ,").unwrap(),
+            [
+                link("a", &[("rel", Some("next"))]),
+                link("b", &[("rel", Some("prev"))]),
+            ]
+        );
+        assert_eq!(parse("").unwrap(), []);
+    }
+
+    #[test]
+    fn quoted_escapes() {
+        let links = parse(r#"<a>; title="say \"hi\", \\ bye"; rel=next"#).unwrap();
+        assert_eq!(links[0].param("title"), Some(r#"say "hi", \ bye"#));
+        assert!(links[0].has_rel("next"));
+    }
+
+    #[test]
+    fn invalid_encoding() {
+        let header = HeaderValue::from_bytes(b"<a>; title=\"\xff\"").unwrap();
+        assert_eq!(
+            parse_header_value(&header).unwrap_err(),
+            LinkHeaderError {
+                link: 0,
+                position: 0,
+                kind: LinkHeaderErrorKind::InvalidEncoding,
+            }
+        );
+    }
+
+    #[test]
+    fn error_kinds_and_positions() {
+        let cases = [
+            ("<a>, b", 1, 5, LinkHeaderErrorKind::ExpectedUri),
+            (
+                "<a>, <b; rel=next",
+                1,
+                6,
+                LinkHeaderErrorKind::UnterminatedUri,
+            ),
+            ("<a>; =next", 0, 5, LinkHeaderErrorKind::ExpectedParamName),
+            ("<a>; rel=", 0, 9, LinkHeaderErrorKind::ExpectedParamValue),
+            (
+                "<a>; rel=\"next",
+                0,
+                9,
+                LinkHeaderErrorKind::UnterminatedQuotedString,
+            ),
+            ("<a> b", 0, 4, LinkHeaderErrorKind::UnexpectedCharacter('b')),
+            (
+                "<a>; type=text/html",
+                0,
+                14,
+                LinkHeaderErrorKind::UnexpectedCharacter('/'),
+            ),
+        ];
+        for (header, link, position, kind) in cases {
+            assert_eq!(
+                error(header),
+                LinkHeaderError {
+                    link,
+                    position,
+                    kind
+                },
+                "{header}"
+            );
+        }
+    }
+
+    #[test]
+    fn error_message() {
+        assert_eq!(
+            error("<a>, b").to_string(),
+            "malformed link #1 at byte 5: expected `<` starting a URI"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Hooks into every request the client sends.
+//!
+//! A [`Middleware`] receives the request along with [`Next`], the rest of the
+//! chain. Whatever it does before calling [`Next::run`] happens before the
+//! request is sent (e.g. adding a request ID header), whatever it does with
+//! the result happens after the response arrived (e.g. counting status codes).
+//! Returning without calling `next` short-circuits the request.
+//!
+//! Middleware added through
+//! [`CanvasBuilder::middleware`](crate::CanvasBuilder::middleware) runs in the
+//! order it was added, between the built-in steps:
+//!
+//! 1. Retries, as configured by the [`RetryPolicy`](crate::retry::RetryPolicy).
+//!    Everything below runs once per attempt.
+//! 2. Custom middleware.
+//! 3. Authentication. The `Authorization` header is only added here, so custom
+//!    middleware never sees the token.
+//! 4. The response cache, if configured. File downloads skip it.
+//! 5. Client side rate limiting.
+//!
+//! At the end of the chain the request is sent, following redirects, and any
+//! response without a success status is turned into the matching
+//! [`CanvasError`](crate::error::CanvasError).
+use crate::{check_status, redirect, CanvasResult};
+
+use futures::future::BoxFuture;
+use reqwest::{Request, Response};
+use std::sync::Arc;
+
+/// A step every request passes through. See the [module docs](self).
+pub trait Middleware: Send + Sync {
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, CanvasResult<Response>>;
+}
+
+/// The remaining middleware of the chain, followed by actually sending the
+/// request.
+///
+/// It can be copied to run the rest of the chain several times, e.g. to
+/// retry. Use [`Request::try_clone`] to get a request for each run.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    client: &'a reqwest::Client,
+    middleware: &'a [Arc<dyn Middleware>],
+    // Set for requests whose responses must not be buffered by the response
+    // cache, i.e. file downloads.
+    pub(crate) uncached: bool,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        client: &'a reqwest::Client,
+        middleware: &'a [Arc<dyn Middleware>],
+        uncached: bool,
+    ) -> Self {
+        Self {
+            client,
+            middleware,
+            uncached,
+        }
+    }
+
+    /// Pass `req` on to the rest of the chain.
+    pub fn run(self, req: Request) -> BoxFuture<'a, CanvasResult<Response>> {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(
+                req,
+                Next {
+                    middleware: rest,
+                    ..self
+                },
+            ),
+            None => Box::pin(async move {
+                let method = req.method().clone();
+                let resp = redirect::execute(self.client, req).await?;
+                check_status(method, resp).await
+            }),
+        }
+    }
+}
@@ -1,6 +1,9 @@
+pub mod accounts;
 pub mod blueprint_courses;
 pub mod courses;
 pub mod enrollments;
+pub mod files;
 pub mod grading_periods;
 pub mod permissions;
+pub mod progress;
 pub mod users;
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Account {
+    // the ID of the Account object
+    pub id: u64,
+    // The display name of the account
+    pub name: String,
+    // The UUID of the account
+    pub uuid: Option<String>,
+    // The account's parent ID, or null if this is the root account
+    pub parent_account_id: Option<u64>,
+    // The ID of the root account, or null if this is the root account
+    pub root_account_id: Option<u64>,
+    // The storage quota for the account in megabytes, if not otherwise specified
+    pub default_storage_quota_mb: Option<u32>,
+    // The storage quota for a user in the account in megabytes, if not otherwise
+    // specified
+    pub default_user_storage_quota_mb: Option<u32>,
+    // The storage quota for a group in the account in megabytes, if not otherwise
+    // specified
+    pub default_group_storage_quota_mb: Option<u32>,
+    // The default time zone of the account. Allowed time zones are
+    // {http://www.iana.org/time-zones IANA time zones} or friendlier
+    // {http://api.rubyonrails.org/classes/ActiveSupport/TimeZone.html Ruby on Rails
+    // time zones}.
+    pub default_time_zone: Option<String>,
+    // The account's identifier in the Student Information System. Only included
+    // if the user has permission to view SIS information.
+    pub sis_account_id: Option<String>,
+    // The account's identifier in the Student Information System. Only included
+    // if the user has permission to view SIS information.
+    pub integration_id: Option<String>,
+    // The id of the SIS import if created through SIS. Only included if the user
+    // has permission to manage SIS information.
+    pub sis_import_id: Option<u64>,
+    // The account's identifier that is sent as context_id in LTI launches.
+    pub lti_guid: Option<String>,
+    // The state of the account. Can be 'active' or 'deleted'.
+    pub workflow_state: String,
+}
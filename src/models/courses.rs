@@ -9,7 +9,7 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use time::OffsetDateTime;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PageType {
     Feed,
@@ -27,7 +27,7 @@ pub enum GradePassbackSetting {
     Empty,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum CourseFormat {
     OnCampus,
@@ -35,7 +35,7 @@ pub enum CourseFormat {
     Blended,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum WorkflowState {
     Unpublished,
@@ -46,7 +46,7 @@ pub enum WorkflowState {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Term {
-    pub id: u32,
+    pub id: u64,
     pub name: String,
     pub start_at: Option<OffsetDateTime>,
     pub end_at: Option<OffsetDateTime>,
@@ -75,7 +75,7 @@ pub struct CalendarLink {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Course {
     // the unique identifier for the course
-    pub id: u32,
+    pub id: u64,
     // the SIS identifier for the course, if defined. This field is only included if
     // the user has permission to view SIS information.
     pub sis_course_id: Option<String>,
@@ -83,10 +83,10 @@ pub struct Course {
     pub uuid: String,
     // the integration identifier for the course, if defined. This field is only
     // included if the user has permission to view SIS information.
-    pub integration_id: Option<u32>,
+    pub integration_id: Option<String>,
     // the unique identifier for the SIS import. This field is only included if the
     // user has permission to manage SIS information.
-    pub sis_import_id: Option<u32>,
+    pub sis_import_id: Option<u64>,
     // the full name of the course. If the requesting user has set a nickname for
     // the course, the nickname will be shown here.
     pub name: String,
@@ -99,15 +99,15 @@ pub struct Course {
     // 'completed', or 'deleted'
     pub workflow_state: WorkflowState,
     // the account associated with the course
-    pub account_id: u32,
+    pub account_id: u64,
     // the root account associated with the course
-    pub root_account_id: u32,
+    pub root_account_id: u64,
     // the enrollment term associated with the course
-    pub enrollment_term_id: u32,
+    pub enrollment_term_id: u64,
     // A list of grading periods associated with the course
     pub grading_periods: Option<Vec<GradingPeriod>>,
     // the grading standard associated with the course
-    pub grading_standard_id: Option<u32>,
+    pub grading_standard_id: Option<u64>,
     // the grade_passback_setting set on the course
     pub grade_passback_setting: Option<GradePassbackSetting>,
     // the date the course was created.
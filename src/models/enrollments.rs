@@ -71,9 +71,9 @@ pub struct Grade {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Enrollment {
     // The ID of the enrollment.
-    pub id: Option<u32>,
+    pub id: Option<u64>,
     // The unique id of the course.
-    pub course_id: Option<u32>,
+    pub course_id: Option<u64>,
     // The SIS Course ID in which the enrollment is associated. Only displayed if
     // present. This field is only included if the user has permission to view SIS
     // information.
@@ -82,7 +82,7 @@ pub struct Enrollment {
     // is only included if the user has permission to view SIS information.
     pub course_integration_id: Option<String>,
     // The unique id of the user's section.
-    pub course_section_id: Option<u32>,
+    pub course_section_id: Option<u64>,
     // The Section Integration ID in which the enrollment is associated. This field
     // is only included if the user has permission to view SIS information.
     pub section_integration_id: Option<String>,
@@ -104,23 +104,23 @@ pub struct Enrollment {
     pub limit_privileges_to_course_section: bool,
     // The unique identifier for the SIS import. This field is only included if the
     // user has permission to manage SIS information.
-    pub sis_import_id: Option<u32>,
+    pub sis_import_id: Option<u64>,
     // The unique id of the user's account.
-    pub root_account_id: Option<u32>,
+    pub root_account_id: Option<u64>,
     // The enrollment type. One of 'StudentEnrollment', 'TeacherEnrollment',
     // 'TaEnrollment', 'DesignerEnrollment', 'ObserverEnrollment'.
     #[serde(rename = "type")]
     pub enrollment_type: EnrollmentType,
     // The unique id of the user.
-    pub user_id: u32,
+    pub user_id: u64,
     // The unique id of the associated user. Will be null unless type is
     // ObserverEnrollment.
-    pub associated_user_id: Option<u32>,
+    pub associated_user_id: Option<u64>,
     // The enrollment role, for course-level permissions. This field will match
     // `type` if the enrollment role has not been customized.
     pub role: EnrollmentType,
     // The id of the enrollment role.
-    pub role_id: u32,
+    pub role_id: u64,
     // The created time of the enrollment, in ISO8601 format.
     #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
     pub created_at: Option<OffsetDateTime>,
@@ -188,7 +188,7 @@ pub struct Enrollment {
     // the course the enrollment belongs to does not have grading periods, or if no
     // currently active grading period exists, the value will be null. (applies only
     // to student enrollments, and only available in course endpoints)
-    pub current_grading_period_id: Option<u32>,
+    pub current_grading_period_id: Option<u64>,
     // The user's override grade for the current grading period.
     pub current_period_override_grade: Option<String>,
     // The user's override score for the current grading period.
@@ -0,0 +1,131 @@
+use crate::timestamps::deserialize_optional_timestamp;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct File {
+    // the ID of the file
+    pub id: u64,
+    pub uuid: Option<String>,
+    // the folder the file is in
+    pub folder_id: Option<u64>,
+    // the name of the file as displayed to users
+    pub display_name: String,
+    // the name of the file as stored
+    pub filename: String,
+    #[serde(rename = "content-type")]
+    pub content_type: Option<String>,
+    // the URL to download the file, including a verifier if needed
+    pub url: Option<String>,
+    // file size in bytes
+    pub size: u64,
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub updated_at: Option<OffsetDateTime>,
+    // the date the file becomes available to students
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub unlock_at: Option<OffsetDateTime>,
+    pub locked: bool,
+    pub hidden: bool,
+    // the date the file becomes unavailable to students
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub lock_at: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub hidden_for_user: bool,
+    pub thumbnail_url: Option<String>,
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub modified_at: Option<OffsetDateTime>,
+    // simplified content-type mapping, e.g. 'image', 'pdf' or 'doc'
+    pub mime_class: Option<String>,
+    // identifier for the file in a third-party media service
+    pub media_entry_id: Option<String>,
+    // whether the current user may not access the file
+    #[serde(default)]
+    pub locked_for_user: bool,
+    pub lock_explanation: Option<String>,
+    // optional: url to the document preview. This url is specific to the user
+    // making the api call. Only included in submission endpoints.
+    pub preview_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Folder {
+    pub id: u64,
+    pub name: String,
+    // the path of the folder from the root folder of its context, e.g.
+    // 'course files/handouts'
+    pub full_name: String,
+    // the type of object the folder belongs to, e.g. 'Course' or 'User'
+    pub context_type: String,
+    pub context_id: u64,
+    // the ID of the folder containing this one, null for a root folder
+    pub parent_folder_id: Option<u64>,
+    // the number of files directly inside the folder
+    pub files_count: u32,
+    // the number of folders directly inside the folder
+    pub folders_count: u32,
+    pub position: Option<u32>,
+    // the API URL listing the files of the folder
+    pub files_url: String,
+    // the API URL listing the folders inside the folder
+    pub folders_url: String,
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub created_at: Option<OffsetDateTime>,
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub updated_at: Option<OffsetDateTime>,
+    // the date the folder becomes available to students
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub unlock_at: Option<OffsetDateTime>,
+    // the date the folder becomes unavailable to students
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub lock_at: Option<OffsetDateTime>,
+    pub locked: bool,
+    pub hidden: Option<bool>,
+    #[serde(default)]
+    pub hidden_for_user: bool,
+    // whether the current user may not access the folder
+    #[serde(default)]
+    pub locked_for_user: bool,
+    // whether the folder holds files submitted to assignments
+    #[serde(default)]
+    pub for_submissions: bool,
+}
+
+/// How much of its storage quota a course, group or user uses, in bytes.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct FileQuota {
+    pub quota: u64,
+    pub quota_used: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_without_optional_fields() {
+        // As returned by some storage services when confirming an upload.
+        let file: File = serde_json::from_str(
+            r#"{"id": 1, "display_name": "a.pdf", "filename": "a.pdf", "size": 3,
+                "locked": false, "hidden": false}"#,
+        )
+        .unwrap();
+        assert_eq!(file.created_at, None);
+        assert!(!file.hidden_for_user && !file.locked_for_user);
+    }
+
+    #[test]
+    fn folder_without_optional_fields() {
+        let folder: Folder = serde_json::from_str(
+            r#"{"id": 1, "name": "course files", "full_name": "course files",
+                "context_type": "Course", "context_id": 2, "parent_folder_id": null,
+                "files_count": 0, "folders_count": 0, "files_url": "", "folders_url": "",
+                "locked": false}"#,
+        )
+        .unwrap();
+        assert_eq!(folder.lock_at, None);
+        assert!(!folder.hidden_for_user && !folder.locked_for_user);
+    }
+}
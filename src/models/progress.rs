@@ -0,0 +1,70 @@
+use crate::timestamps::deserialize_optional_timestamp;
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// The state of a job Canvas runs in the background, e.g. a course copy or a
+/// content export.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Progress {
+    // the ID of the Progress object
+    pub id: u64,
+    // the context owning the job.
+    pub context_id: u64,
+    pub context_type: String,
+    // the id of the user who started the job
+    pub user_id: Option<u64>,
+    // the type of operation
+    pub tag: String,
+    // percent completed
+    pub completion: Option<f64>,
+    // the state of the job one of 'queued', 'running', 'completed', 'failed'
+    pub workflow_state: ProgressState,
+    // the time the job was created
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub created_at: Option<OffsetDateTime>,
+    // the time the job was last updated
+    #[serde(deserialize_with = "deserialize_optional_timestamp", default)]
+    pub updated_at: Option<OffsetDateTime>,
+    // optional details about the job
+    pub message: Option<String>,
+    // optional results of the job. omitted when job is still pending
+    pub results: Option<serde_json::Value>,
+    // url where a progress update can be retrieved
+    pub url: Option<String>,
+}
+
+impl Progress {
+    /// Whether the job has completed or failed.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.workflow_state,
+            ProgressState::Completed | ProgressState::Failed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_without_timestamps() {
+        let progress: Progress = serde_json::from_str(
+            r#"{"id": 1, "context_id": 2, "context_type": "Course", "tag": "course_batch_update",
+                "workflow_state": "queued"}"#,
+        )
+        .unwrap();
+        assert_eq!(progress.created_at, None);
+        assert!(!progress.is_finished());
+    }
+}
@@ -21,7 +21,7 @@ pub enum AvatarState {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UserDisplay {
     // The ID of the user.
-    pub id: u32,
+    pub id: u64,
     // A short name the user has selected, for use in conversations or other less
     // formal places through the site.
     pub short_name: String,
@@ -50,7 +50,7 @@ pub struct AnonymousUserDisplay {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct User {
     // The ID of the user.
-    pub id: u32,
+    pub id: u64,
     // The name of the user.
     pub name: String,
     // The name of the user that is should be used for sorting groups of users, such
@@ -68,7 +68,7 @@ pub struct User {
     pub sis_user_id: Option<String>,
     // The id of the SIS import.  This field is only included if the user came from
     // a SIS import and has permissions to manage SIS information.
-    pub sis_import_id: Option<u32>,
+    pub sis_import_id: Option<u64>,
     // The integration_id associated with the user.  This field is only included if
     // the user came from a SIS import and has permissions to view SIS information.
     pub integration_id: Option<String>,
@@ -0,0 +1,280 @@
+//! OAuth2 authorization code flow, for applications that act on behalf of
+//! Canvas users instead of using a static access token.
+//!
+//! 1. Send the user to [`OAuthClient::authorization_url`].
+//! 2. Canvas redirects back to the redirect URI with a `code`, which
+//!    [`OAuthClient::exchange_code`] turns into a [`TokenSet`].
+//! 3. Save the tokens in a [`TokenStore`] and build the client with
+//!    [`CanvasBuilder::oauth`](crate::CanvasBuilder::oauth). Expired access
+//!    tokens are then refreshed automatically.
+use crate::{
+    builder::normalise_base_url,
+    check_status,
+    error::{CanvasError, OAuthError},
+    CanvasResult,
+};
+
+use futures::future::BoxFuture;
+use reqwest::{header::HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use time::{Duration, OffsetDateTime};
+
+// Refresh access tokens this long before they actually expire, so that they
+// do not run out while a request is in flight.
+const EXPIRY_MARGIN: Duration = Duration::seconds(60);
+
+const REDACTED: &str = "[REDACTED]";
+
+/// The credentials of a developer key.
+#[derive(Clone)]
+pub struct OAuthConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    // Must match the redirect URI configured for the developer key.
+    pub redirect_uri: String,
+}
+
+/// The user an access token was issued to.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct OAuthUser {
+    pub id: u64,
+    pub name: String,
+}
+
+/// The tokens Canvas issued for a user.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct TokenSet {
+    pub access_token: String,
+    // Only returned when the tokens are first issued; refreshing keeps the
+    // existing refresh token.
+    pub refresh_token: Option<String>,
+    #[serde(with = "time::serde::rfc3339::option", default)]
+    pub expires_at: Option<OffsetDateTime>,
+    pub user: Option<OAuthUser>,
+}
+
+// Secrets are redacted, so that the configuration can be logged safely.
+impl std::fmt::Debug for OAuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuthConfig")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &REDACTED)
+            .field("redirect_uri", &self.redirect_uri)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for TokenSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenSet")
+            .field("access_token", &REDACTED)
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| REDACTED),
+            )
+            .field("expires_at", &self.expires_at)
+            .field("user", &self.user)
+            .finish()
+    }
+}
+
+impl TokenSet {
+    /// Whether the access token has expired or is about to.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at - EXPIRY_MARGIN <= OffsetDateTime::now_utc())
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+    user: Option<OAuthUser>,
+}
+
+impl From<TokenResponse> for TokenSet {
+    fn from(resp: TokenResponse) -> Self {
+        Self {
+            access_token: resp.access_token,
+            refresh_token: resp.refresh_token,
+            expires_at: resp
+                .expires_in
+                .map(|seconds| OffsetDateTime::now_utc() + Duration::seconds(seconds)),
+            user: resp.user,
+        }
+    }
+}
+
+/// Where the tokens of a user are kept between requests, e.g. a database row
+/// or the session of a web application.
+pub trait TokenStore: Send + Sync {
+    /// The current tokens, if any have been stored yet.
+    fn load(&self) -> BoxFuture<'_, CanvasResult<Option<TokenSet>>>;
+    /// Replace the stored tokens, called after every refresh.
+    fn save(&self, tokens: TokenSet) -> BoxFuture<'_, CanvasResult<()>>;
+}
+
+/// A [`TokenStore`] that only keeps the tokens in memory.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStore {
+    tokens: Mutex<Option<TokenSet>>,
+}
+
+impl MemoryTokenStore {
+    pub fn new(tokens: TokenSet) -> Self {
+        Self {
+            tokens: Mutex::new(Some(tokens)),
+        }
+    }
+}
+
+impl TokenStore for MemoryTokenStore {
+    fn load(&self) -> BoxFuture<'_, CanvasResult<Option<TokenSet>>> {
+        let tokens = self.tokens.lock().unwrap().clone();
+        Box::pin(async { Ok(tokens) })
+    }
+
+    fn save(&self, tokens: TokenSet) -> BoxFuture<'_, CanvasResult<()>> {
+        *self.tokens.lock().unwrap() = Some(tokens);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Talks to the OAuth2 endpoints of a Canvas instance.
+#[derive(Debug, Clone)]
+pub struct OAuthClient {
+    config: OAuthConfig,
+    base_url: String,
+    pub(crate) http: reqwest::Client,
+}
+
+impl OAuthClient {
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// `base_url` is the root URL of the Canvas instance, as passed to
+    /// [`CanvasBuilder::base_url`](crate::CanvasBuilder::base_url).
+    ///
+    /// Plain `http://` URLs are accepted here, so that local development
+    /// instances work; [`CanvasBuilder::build`](crate::CanvasBuilder::build)
+    /// still rejects them unless
+    /// [`allow_http`](crate::CanvasBuilder::allow_http) is set.
+    pub fn new(base_url: &str, config: OAuthConfig) -> CanvasResult<Self> {
+        Ok(Self {
+            config,
+            base_url: normalise_base_url(base_url, true)?,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    /// The URL to send the user to for granting access. `state` is handed
+    /// back unchanged in the redirect and should be checked to prevent CSRF.
+    /// Leave `scopes` empty to request access to everything the developer key
+    /// allows.
+    pub fn authorization_url(&self, state: &str, scopes: &[&str]) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("response_type", "code")
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("state", state);
+        if !scopes.is_empty() {
+            query.append_pair("scope", &scopes.join(" "));
+        }
+        format!("{}/login/oauth2/auth?{}", self.base_url, query.finish())
+    }
+
+    /// Exchange the `code` from the redirect for a set of tokens.
+    pub async fn exchange_code(&self, code: &str) -> CanvasResult<TokenSet> {
+        self.request_tokens(&[
+            ("grant_type", "authorization_code"),
+            ("client_id", &self.config.client_id),
+            ("client_secret", &self.config.client_secret),
+            ("redirect_uri", &self.config.redirect_uri),
+            ("code", code),
+        ])
+        .await
+    }
+
+    /// Get a new access token using a refresh token.
+    pub async fn refresh(&self, refresh_token: &str) -> CanvasResult<TokenSet> {
+        let mut tokens = self
+            .request_tokens(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", &self.config.client_id),
+                ("client_secret", &self.config.client_secret),
+                ("refresh_token", refresh_token),
+            ])
+            .await?;
+        tokens
+            .refresh_token
+            .get_or_insert_with(|| refresh_token.to_string());
+        Ok(tokens)
+    }
+
+    async fn request_tokens(&self, params: &[(&str, &str)]) -> CanvasResult<TokenSet> {
+        let resp = self
+            .http
+            .post(format!("{}/login/oauth2/token", self.base_url))
+            .form(params)
+            .send()
+            .await?;
+        let resp = check_status(Method::POST, resp).await?;
+        Ok(resp.json::<TokenResponse>().await?.into())
+    }
+}
+
+/// The tokens of a client authenticated through OAuth2.
+pub(crate) struct OAuthSession {
+    client: OAuthClient,
+    store: Arc<dyn TokenStore>,
+    // Makes sure that concurrent requests that all find the token expired only
+    // refresh it once.
+    refresh_lock: tokio::sync::Mutex<()>,
+}
+
+impl OAuthSession {
+    pub(crate) fn new(client: OAuthClient, store: Arc<dyn TokenStore>) -> Self {
+        Self {
+            client,
+            store,
+            refresh_lock: Default::default(),
+        }
+    }
+
+    /// The access token to send, refreshed first if it has expired.
+    pub(crate) async fn access_token(&self) -> CanvasResult<String> {
+        let tokens = self.store.load().await?.ok_or(OAuthError::NoTokens)?;
+        if tokens.is_expired() {
+            return Ok(self.refresh(&tokens.access_token).await?.access_token);
+        }
+        Ok(tokens.access_token)
+    }
+
+    /// Refresh the tokens after Canvas rejected `stale_access_token`, unless
+    /// another request already did so in the meantime.
+    pub(crate) async fn refresh(&self, stale_access_token: &str) -> CanvasResult<TokenSet> {
+        let _guard = self.refresh_lock.lock().await;
+        let tokens = self.store.load().await?.ok_or(OAuthError::NoTokens)?;
+        if tokens.access_token != stale_access_token {
+            return Ok(tokens);
+        }
+
+        let refresh_token = tokens.refresh_token.ok_or(OAuthError::NoRefreshToken)?;
+        let tokens = self.client.refresh(&refresh_token).await?;
+        self.store.save(tokens.clone()).await?;
+        Ok(tokens)
+    }
+}
+
+/// The `Authorization` header for an access token.
+pub(crate) fn bearer(access_token: &str) -> CanvasResult<HeaderValue> {
+    let mut value = HeaderValue::from_str(&format!("Bearer {}", access_token))
+        .map_err(|_| CanvasError::OAuth(OAuthError::InvalidToken))?;
+    value.set_sensitive(true);
+    Ok(value)
+}
@@ -0,0 +1,205 @@
+use crate::{link_header, CanvasResult};
+
+use reqwest::header::HeaderValue;
+use url::Url;
+
+/// The pagination links Canvas returns in the `Link` header of every page.
+#[derive(Debug, Default, Clone)]
+pub struct PaginationInfo {
+    pub current_url: Option<String>,
+    pub next_url: Option<String>,
+    pub prev_url: Option<String>,
+    pub first_url: Option<String>,
+    pub last_url: Option<String>,
+}
+
+impl PaginationInfo {
+    /// Whether there are more pages after this one.
+    pub fn has_next(&self) -> bool {
+        self.next_url.is_some()
+    }
+}
+
+/// The URLs of all pages from `next_url` up to and including `last_url`, if
+/// Canvas numbers the pages of this endpoint. Endpoints that paginate with
+/// opaque bookmarks (`page=bookmark:...`) return `None`.
+pub(crate) fn numbered_page_urls(info: &PaginationInfo) -> Option<Vec<String>> {
+    let next = Url::parse(info.next_url.as_deref()?).ok()?;
+    let last = Url::parse(info.last_url.as_deref()?).ok()?;
+    let (from, to) = (page_number(&next)?, page_number(&last)?);
+    if to < from {
+        return None;
+    }
+    Some((from..=to).map(|page| with_page(&next, page)).collect())
+}
+
+fn page_number(url: &Url) -> Option<u64> {
+    url.query_pairs()
+        .find(|(key, _)| key == "page")
+        .and_then(|(_, page)| page.parse().ok())
+}
+
+fn with_page(url: &Url, page: u64) -> String {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if key == "page" {
+                page.to_string()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+    url.to_string()
+}
+
+/// One page of a paginated endpoint.
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub pagination: PaginationInfo,
+}
+
+/// Collect the pagination links from the `Link` header of a response to
+/// `request_url`. Relative links are resolved against `request_url`.
+pub(crate) fn parse_pagination_info(
+    link_header: Option<&HeaderValue>,
+    request_url: &Url,
+) -> CanvasResult<PaginationInfo> {
+    let mut info: PaginationInfo = Default::default();
+    let Some(links) = link_header else {
+        return Ok(info);
+    };
+
+    for link in link_header::parse_header_value(links)? {
+        let uri = request_url
+            .join(&link.uri)
+            .map(String::from)
+            .unwrap_or(link.uri.clone());
+        for rel in link.rels() {
+            let slot = match rel.to_ascii_lowercase().as_str() {
+                "current" => &mut info.current_url,
+                "prev" | "previous" => &mut info.prev_url,
+                "next" => &mut info.next_url,
+                "first" => &mut info.first_url,
+                "last" => &mut info.last_url,
+                _ => continue,
+            };
+            slot.get_or_insert_with(|| uri.clone());
+        }
+    }
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::CanvasError;
+
+    fn info(header: &str) -> PaginationInfo {
+        let request_url = Url::parse("https://x.test/api/v1/courses?page=1").unwrap();
+        parse_pagination_info(Some(&HeaderValue::from_str(header).unwrap()), &request_url).unwrap()
+    }
+
+    #[test]
+    fn collects_links_by_rel() {
+        let info = info(
+            r#"<https://x.test/api/v1/courses?page=1>; rel="current first", <https://x.test/api/v1/courses?page=2>; rel="next", <https://x.test/api/v1/courses?page=3>; rel="last""#,
+        );
+        let page = |n| Some(format!("https://x.test/api/v1/courses?page={n}"));
+        assert_eq!(info.current_url, page(1));
+        assert_eq!(info.first_url, page(1));
+        assert_eq!(info.next_url, page(2));
+        assert_eq!(info.last_url, page(3));
+        assert_eq!(info.prev_url, None);
+        assert!(info.has_next());
+    }
+
+    #[test]
+    fn resolves_relative_links() {
+        let info = info(r#"</api/v1/courses?page=2>; rel="next", <?page=0>; rel="prev""#);
+        assert_eq!(
+            info.next_url.as_deref(),
+            Some("https://x.test/api/v1/courses?page=2")
+        );
+        assert_eq!(
+            info.prev_url.as_deref(),
+            Some("https://x.test/api/v1/courses?page=0")
+        );
+    }
+
+    #[test]
+    fn accepts_previous() {
+        let info = info(r#"<https://x.test/a>; rel="previous""#);
+        assert_eq!(info.prev_url.as_deref(), Some("https://x.test/a"));
+    }
+
+    #[test]
+    fn first_occurrence_wins() {
+        let info = info(r#"<https://x.test/a>; rel="next", <https://x.test/b>; rel="next last""#);
+        assert_eq!(info.next_url.as_deref(), Some("https://x.test/a"));
+        assert_eq!(info.last_url.as_deref(), Some("https://x.test/b"));
+    }
+
+    #[test]
+    fn missing_header() {
+        let request_url = Url::parse("https://x.test/api/v1/courses").unwrap();
+        let info = parse_pagination_info(None, &request_url).unwrap();
+        assert!(!info.has_next());
+        assert_eq!(info.last_url, None);
+    }
+
+    #[test]
+    fn malformed_header() {
+        let request_url = Url::parse("https://x.test/api/v1/courses").unwrap();
+        let header = HeaderValue::from_static("https://x.test/a; rel=next");
+        assert!(matches!(
+            parse_pagination_info(Some(&header), &request_url),
+            Err(CanvasError::Pagination(_))
+        ));
+    }
+
+    #[test]
+    fn numbers_pages_up_to_last() {
+        let info = PaginationInfo {
+            next_url: Some("https://x.test/api/v1/courses?per_page=10&page=2".to_string()),
+            last_url: Some("https://x.test/api/v1/courses?per_page=10&page=4".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            numbered_page_urls(&info).unwrap(),
+            [
+                "https://x.test/api/v1/courses?per_page=10&page=2",
+                "https://x.test/api/v1/courses?per_page=10&page=3",
+                "https://x.test/api/v1/courses?per_page=10&page=4",
+            ]
+        );
+    }
+
+    #[test]
+    fn bookmarks_are_not_numbered() {
+        let urls = |next: &str, last: Option<&str>| {
+            numbered_page_urls(&PaginationInfo {
+                next_url: Some(next.to_string()),
+                last_url: last.map(str::to_string),
+                ..Default::default()
+            })
+        };
+        assert_eq!(
+            urls(
+                "https://x.test/a?page=bookmark:WzFd",
+                Some("https://x.test/a?page=bookmark:WzJd")
+            ),
+            None
+        );
+        assert_eq!(urls("https://x.test/a?page=2", None), None);
+        assert_eq!(
+            urls("https://x.test/a?page=3", Some("https://x.test/a?page=2")),
+            None
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Options accepted by the list endpoints, passed through the request builders
+//! returned by e.g. `canvas.courses().list()`.
+use serde::Serialize;
+
+/// The direction to sort results in.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Additional information to include with each course.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseInclude {
+    NeedsGradingCount,
+    SyllabusBody,
+    PublicDescription,
+    TotalScores,
+    CurrentGradingPeriodScores,
+    GradingPeriods,
+    Term,
+    Account,
+    CourseProgress,
+    Sections,
+    StorageQuotaUsedMb,
+    TotalStudents,
+    PassbackStatus,
+    Favorites,
+    Teachers,
+    ObservedUsers,
+    Tabs,
+    CourseImage,
+    BannerImage,
+    Concluded,
+    PostManually,
+}
+
+/// Only return courses where the user is enrolled as this type.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseEnrollmentType {
+    Teacher,
+    Student,
+    Ta,
+    Observer,
+    Designer,
+}
+
+/// Only return courses where the user's enrollment is in this state.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseEnrollmentState {
+    Active,
+    InvitedOrPending,
+    Completed,
+}
+
+/// Additional information to include with each user.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserInclude {
+    AvatarUrl,
+    Email,
+    LastLogin,
+    TimeZone,
+    Uuid,
+}
+
+/// Only return users enrolled as this type in at least one course.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserEnrollmentType {
+    Student,
+    Teacher,
+    Ta,
+    Observer,
+    Designer,
+}
+
+/// The column to sort users by.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSort {
+    Username,
+    Email,
+    SisId,
+    IntegrationId,
+    LastLogin,
+}
+
+/// The column to sort files by.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSort {
+    Name,
+    Size,
+    CreatedAt,
+    UpdatedAt,
+    ContentType,
+    User,
+}
+
+/// Additional information to include with each account.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountInclude {
+    LtiGuid,
+    RegistrationSettings,
+    Services,
+}
+
+/// Only return courses of an account in these states.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountCourseState {
+    Created,
+    Claimed,
+    Available,
+    Completed,
+    Deleted,
+    All,
+}
+
+/// The column to sort the courses of an account by.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountCourseSort {
+    CourseStatus,
+    CourseName,
+    SisCourseId,
+    Teacher,
+    AccountName,
+}
+
+/// What the search term of an account course search is matched against.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseSearchBy {
+    Course,
+    Teacher,
+}
@@ -0,0 +1,264 @@
+use crate::{
+    error::CanvasError,
+    middleware::{Middleware, Next},
+    redirect::RedirectHeaders,
+    CanvasResult,
+};
+
+use futures::future::BoxFuture;
+use reqwest::{header::HeaderMap, Request, Response};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const REMAINING_HEADER: &str = "x-rate-limit-remaining";
+const COST_HEADER: &str = "x-request-cost";
+
+/// Tuning knobs for the client side throttling.
+///
+/// Canvas throttles each token with a leaky bucket: every request costs some
+/// units, the bucket slowly refills, and requests are rejected with
+/// `403 Forbidden (Rate Limit Exceeded)` once it runs dry. The client tracks the
+/// bucket through the `X-Rate-Limit-Remaining` and `X-Request-Cost` headers and
+/// holds requests back before that happens.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Size of the bucket. Canvas uses 700 by default.
+    pub capacity: f64,
+    /// How many units the bucket is assumed to regain per second.
+    pub refill_per_second: f64,
+    /// The cost Canvas charges up front for every request while it is in
+    /// flight, refunded once it completes.
+    pub upfront_cost: f64,
+    /// Below this many remaining units, requests are paced so that the bucket
+    /// stops shrinking.
+    pub slow_down_below: f64,
+    /// Below this many remaining units, requests are held back until the bucket
+    /// has refilled above it again.
+    pub pause_below: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 700.0,
+            refill_per_second: 10.0,
+            upfront_cost: 50.0,
+            slow_down_below: 300.0,
+            pause_below: 100.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BucketState {
+    // Remaining units as last reported by Canvas, minus what has been reserved
+    // for requests sent since.
+    remaining: f64,
+    // When `remaining` was last brought up to date.
+    updated_at: Instant,
+    // Running average of the cost reported for past requests.
+    average_cost: f64,
+}
+
+/// Shared view of the rate limit budget of one token.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    config: RateLimitConfig,
+    state: Mutex<BucketState>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        let state = BucketState {
+            remaining: config.capacity,
+            updated_at: Instant::now(),
+            average_cost: 1.0,
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// Estimated remaining budget, accounting for the refill since the last
+    /// update.
+    pub(crate) fn remaining(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+        self.estimate(&state, Instant::now())
+    }
+
+    fn estimate(&self, state: &BucketState, now: Instant) -> f64 {
+        let refilled =
+            now.duration_since(state.updated_at).as_secs_f64() * self.config.refill_per_second;
+        (state.remaining + refilled).min(self.config.capacity)
+    }
+
+    /// Wait until the budget allows another request and reserve its cost,
+    /// which is returned.
+    pub(crate) async fn acquire(&self) -> f64 {
+        let (cost, delay) = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let remaining = self.estimate(&state, now);
+            let cost = self.config.upfront_cost + state.average_cost;
+
+            let delay = if remaining < self.config.pause_below {
+                (self.config.pause_below + cost - remaining) / self.config.refill_per_second
+            } else if remaining < self.config.slow_down_below {
+                state.average_cost / self.config.refill_per_second
+            } else {
+                0.0
+            };
+
+            // Reserve the budget now, so that concurrent callers see it gone
+            // before this request has even been sent. The refill during the
+            // delay is covered by the estimate.
+            state.remaining = remaining - cost;
+            state.updated_at = now;
+            (cost, delay)
+        };
+
+        if delay > 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+        }
+        cost
+    }
+
+    /// Bring the budget up to date with the headers of a response to a
+    /// request that reserved `reserved` units.
+    ///
+    /// Without a remaining budget to go by, e.g. for responses of file
+    /// storage services, the upfront cost is refunded and only the reported
+    /// cost, if any, is charged.
+    pub(crate) fn update(&self, headers: &HeaderMap, reserved: f64) {
+        let parse = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<f64>().ok())
+        };
+
+        let mut state = self.state.lock().unwrap();
+        let cost = parse(COST_HEADER);
+        if let Some(cost) = cost {
+            state.average_cost = 0.8 * state.average_cost + 0.2 * cost;
+        }
+        match parse(REMAINING_HEADER) {
+            Some(remaining) => {
+                state.remaining = remaining;
+                state.updated_at = Instant::now();
+            }
+            None => {
+                let charged = match cost {
+                    Some(cost) => cost,
+                    None => reserved - self.config.upfront_cost,
+                };
+                state.remaining = (state.remaining + reserved - charged).min(self.config.capacity);
+            }
+        }
+    }
+
+    /// Record that Canvas rejected a request because the bucket ran dry.
+    pub(crate) fn exhausted(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.remaining = 0.0;
+        state.updated_at = Instant::now();
+    }
+}
+
+impl Middleware for RateLimiter {
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, CanvasResult<Response>> {
+        Box::pin(async move {
+            let reserved = self.acquire().await;
+            let result = next.run(req).await;
+            match &result {
+                Ok(resp) => self.update(budget_headers(resp), reserved),
+                Err(e) => {
+                    match e.api_error() {
+                        Some(api_error) => self.update(&api_error.headers, reserved),
+                        // Never reached Canvas, or got cut off on the way.
+                        None => self.update(&HeaderMap::new(), reserved),
+                    }
+                    if let CanvasError::Throttled(_) = e {
+                        self.exhausted();
+                    }
+                }
+            }
+            result
+        })
+    }
+}
+
+// Downloads are redirected from Canvas to a storage service, whose response
+// says nothing about the budget; the last redirect that does is used instead.
+fn budget_headers(resp: &Response) -> &HeaderMap {
+    if resp.headers().contains_key(REMAINING_HEADER) {
+        return resp.headers();
+    }
+    resp.extensions()
+        .get::<RedirectHeaders>()
+        .and_then(|hops| {
+            hops.0
+                .iter()
+                .rev()
+                .find(|headers| headers.contains_key(REMAINING_HEADER))
+        })
+        .unwrap_or(resp.headers())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn refunds_the_upfront_cost_without_a_remaining_header() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            refill_per_second: 0.0,
+            ..Default::default()
+        });
+        for _ in 0..20 {
+            let reserved = limiter.acquire().await;
+            limiter.update(&HeaderMap::new(), reserved);
+        }
+        // Only the average cost of 1 is charged for each request.
+        assert_eq!(limiter.remaining(), 680.0);
+
+        let reserved = limiter.acquire().await;
+        limiter.update(&headers(&[(COST_HEADER, "3")]), reserved);
+        assert_eq!(limiter.remaining(), 677.0);
+    }
+
+    #[tokio::test]
+    async fn takes_the_remaining_budget_from_canvas() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            refill_per_second: 0.0,
+            ..Default::default()
+        });
+        let reserved = limiter.acquire().await;
+        limiter.update(
+            &headers(&[(REMAINING_HEADER, "400.5"), (COST_HEADER, "2")]),
+            reserved,
+        );
+        assert_eq!(limiter.remaining(), 400.5);
+    }
+
+    #[test]
+    fn reads_the_budget_from_the_last_redirect() {
+        let mut resp = reqwest::Response::from(http::Response::new(""));
+        assert!(budget_headers(&resp).is_empty());
+
+        resp.extensions_mut().insert(RedirectHeaders(vec![
+            headers(&[(REMAINING_HEADER, "500")]),
+            headers(&[(REMAINING_HEADER, "400")]),
+            HeaderMap::new(),
+        ]));
+        assert_eq!(budget_headers(&resp)[REMAINING_HEADER], "400");
+    }
+}
@@ -0,0 +1,255 @@
+//! Redirect handling.
+//!
+//! The HTTP client is built without automatic redirects, as it only strips
+//! credentials when the host changes, not when e.g. an `https` URL redirects to
+//! `http` on the same host. Redirects are followed here instead, dropping the
+//! `Authorization` header for good as soon as a redirect leaves the origin of
+//! the original request.
+use crate::{error::CanvasError, CanvasResult};
+
+use reqwest::{
+    header::{
+        HeaderMap, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION,
+        PROXY_AUTHORIZATION,
+    },
+    Method, Request, Response, StatusCode,
+};
+use url::{Origin, Url};
+
+const MAX_REDIRECTS: usize = 10;
+
+/// The headers of the redirects followed to get a response, first to last,
+/// kept as an extension of the final response.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RedirectHeaders(pub(crate) Vec<HeaderMap>);
+
+/// Send `req`, following up to 10 redirects.
+pub(crate) async fn execute(client: &reqwest::Client, mut req: Request) -> CanvasResult<Response> {
+    let origin = req.url().origin();
+    let mut hops = Vec::new();
+    let finish = |mut resp: Response, hops: Vec<HeaderMap>| {
+        if !hops.is_empty() {
+            resp.extensions_mut().insert(RedirectHeaders(hops));
+        }
+        Ok(resp)
+    };
+    for _ in 0..MAX_REDIRECTS {
+        // Keep a copy to replay against the redirect target. Requests with a
+        // streaming body cannot be copied, and are not redirected.
+        let retry = req.try_clone();
+        let resp = client.execute(req).await?;
+        let status = resp.status();
+        let (Some(next), true) = (retry, status.is_redirection()) else {
+            return finish(resp, hops);
+        };
+        let Some(location) = resp
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| resp.url().join(location).ok())
+        else {
+            return finish(resp, hops);
+        };
+
+        let Some(next) = redirect(next, status, location, &origin) else {
+            return finish(resp, hops);
+        };
+        hops.push(resp.headers().clone());
+        req = next;
+    }
+    Err(CanvasError::TooManyRedirects(req.url().to_string()))
+}
+
+/// Turn `next`, a copy of the request answered with `status`, into the
+/// request for `location`. Returns `None` for statuses that are not followed.
+fn redirect(
+    mut next: Request,
+    status: StatusCode,
+    location: Url,
+    origin: &Origin,
+) -> Option<Request> {
+    match status {
+        // Resend as is.
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {}
+        // Browsers turn these into a GET without body, except for HEAD.
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => {
+            if next.method() != Method::HEAD {
+                *next.method_mut() = Method::GET;
+                *next.body_mut() = None;
+                next.headers_mut().remove(CONTENT_TYPE);
+                next.headers_mut().remove(CONTENT_LENGTH);
+            }
+        }
+        // `304 Not Modified` and the like.
+        _ => return None,
+    }
+
+    if location.origin() != *origin {
+        for header in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION] {
+            next.headers_mut().remove(header);
+        }
+    }
+    *next.url_mut() = location;
+    Some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{response, serve};
+    use crate::Canvas;
+    use reqwest::header::HeaderValue;
+    use std::sync::{Arc, Mutex};
+
+    fn with_credentials(method: Method, url: &str) -> Request {
+        let mut req = Request::new(method, Url::parse(url).unwrap());
+        let headers = req.headers_mut();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        headers.insert(COOKIE, HeaderValue::from_static("session=1"));
+        headers.insert(
+            PROXY_AUTHORIZATION,
+            HeaderValue::from_static("Basic cHJveHk="),
+        );
+        req
+    }
+
+    fn credentials(req: &Request) -> Vec<&HeaderValue> {
+        [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION]
+            .iter()
+            .filter_map(|header| req.headers().get(header))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn cross_origin_redirects_drop_credentials() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        let (target_url, _) = serve(move |_, headers| {
+            log.lock().unwrap().push(headers.clone());
+            response("200 OK", "", "{}")
+        });
+        let (base_url, requests) = serve(move |request_line, _| {
+            let location = if request_line.contains(" /same ") {
+                "/other"
+            } else {
+                &format!("{target_url}/target")
+            };
+            response("302 Found", &format!("location: {location}\r\n"), "")
+        });
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let req = with_credentials(Method::POST, &format!("{base_url}/same"));
+        execute(&client, req).await.unwrap();
+
+        // The first redirect stays on the origin, the second leaves it.
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["POST /same HTTP/1.1", "GET /other HTTP/1.1"]
+        );
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        for header in [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION] {
+            assert!(!received[0].contains_key(header));
+        }
+    }
+
+    #[test]
+    fn same_origin_redirects_keep_credentials() {
+        let req = with_credentials(Method::GET, "https://canvas.test/a");
+        let origin = req.url().origin();
+        let next = redirect(
+            req,
+            StatusCode::FOUND,
+            Url::parse("https://canvas.test/b").unwrap(),
+            &origin,
+        )
+        .unwrap();
+        assert_eq!(credentials(&next).len(), 3);
+    }
+
+    #[test]
+    fn downgrades_to_http_are_cross_origin() {
+        let req = with_credentials(Method::GET, "https://canvas.test/a");
+        let origin = req.url().origin();
+        let next = redirect(
+            req,
+            StatusCode::TEMPORARY_REDIRECT,
+            Url::parse("http://canvas.test/a").unwrap(),
+            &origin,
+        )
+        .unwrap();
+        assert_eq!(next.url().as_str(), "http://canvas.test/a");
+        assert!(credentials(&next).is_empty());
+    }
+
+    #[test]
+    fn see_other_turns_into_a_get() {
+        let mut req = with_credentials(Method::POST, "https://canvas.test/a");
+        *req.body_mut() = Some("x=1".into());
+        req.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        let origin = req.url().origin();
+        let next = redirect(
+            req,
+            StatusCode::SEE_OTHER,
+            Url::parse("https://canvas.test/b").unwrap(),
+            &origin,
+        )
+        .unwrap();
+        assert_eq!(next.method(), Method::GET);
+        assert!(next.body().is_none());
+        assert!(!next.headers().contains_key(CONTENT_TYPE));
+    }
+
+    #[tokio::test]
+    async fn stops_after_ten_redirects() {
+        let (base_url, requests) = serve(|_, _| response("302 Found", "location: /loop\r\n", ""));
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let req = Request::new(Method::GET, Url::parse(&base_url).unwrap());
+
+        let result = execute(&client, req).await;
+        assert!(
+            matches!(&result, Err(CanvasError::TooManyRedirects(url)) if url.ends_with("/loop")),
+            "{result:?}"
+        );
+        assert_eq!(requests.lock().unwrap().len(), MAX_REDIRECTS);
+    }
+
+    #[tokio::test]
+    async fn foreign_urls_need_to_be_allowed() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let log = received.clone();
+        let (foreign_url, _) = serve(move |_, headers| {
+            log.lock().unwrap().push(headers.clone());
+            response("200 OK", "", "{}")
+        });
+        let canvas = |allow| {
+            Canvas::builder()
+                .base_url("http://canvas.test")
+                .allow_http(true)
+                .allow_foreign_urls(allow)
+                .token("token")
+                .build()
+                .unwrap()
+        };
+        let url = format!("{foreign_url}/file");
+
+        let result = canvas(false).get(&url, None).await;
+        assert!(
+            matches!(&result, Err(CanvasError::ForeignUrl(foreign)) if *foreign == url),
+            "{result:?}"
+        );
+        assert!(received.lock().unwrap().is_empty());
+
+        canvas(true).get(&url, None).await.unwrap();
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(!received[0].contains_key(AUTHORIZATION));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    error::CanvasError,
+    middleware::{Middleware, Next},
+    CanvasResult,
+};
+
+use futures::future::BoxFuture;
+use reqwest::{header::HeaderMap, Request, Response, StatusCode};
+use std::{future::Future, time::Duration};
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+/// When and how often failed requests are retried.
+///
+/// Only idempotent requests are retried. Pages fetched while streaming a
+/// paginated endpoint are retried individually, so a stream resumes where it
+/// left off instead of failing.
+///
+/// The policy is the outermost [`Middleware`] of the client; every attempt
+/// runs through the rest of the chain again. As the middleware only sees the
+/// response head, decoded `GET` responses whose body is cut off halfway are
+/// retried by the client around the whole fetch.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// How often a request is attempted in total, including the first try.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_backoff: Duration,
+    /// Factor by which the delay grows after every attempt.
+    pub multiplier: f64,
+    /// Fraction of the delay that is randomised, between `0.0` (none) and
+    /// `1.0` (anywhere between zero and the full delay).
+    pub jitter: f64,
+    /// Response statuses that are worth retrying.
+    pub retry_statuses: Vec<StatusCode>,
+    /// Retry requests rejected with `403 Forbidden (Rate Limit Exceeded)`.
+    pub retry_throttled: bool,
+    /// Retry requests that failed to connect, timed out or were cut off before
+    /// the response, or its body, arrived in full.
+    pub retry_transport_errors: bool,
+    /// Wait for as long as the `Retry-After` header asks, instead of the
+    /// computed backoff.
+    pub respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retry_statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_throttled: true,
+            retry_transport_errors: true,
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a request that failed with `error` on attempt number `attempt`
+    /// (starting at 1) should be tried again.
+    pub(crate) fn should_retry(&self, error: &CanvasError, attempt: u32) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        match error {
+            // Reqwest reports a body cut off while reading it as a decode
+            // error; JSON is decoded separately.
+            CanvasError::Reqwest(e) => {
+                self.retry_transport_errors
+                    && (e.is_connect() || e.is_timeout() || e.is_body() || e.is_decode())
+            }
+            CanvasError::Throttled(_) if self.retry_throttled => true,
+            _ => error
+                .status()
+                .is_some_and(|status| self.retry_statuses.contains(&status)),
+        }
+    }
+
+    /// Run `fetch` until it succeeds, retrying it when the body of its
+    /// response is cut off.
+    ///
+    /// Everything else, from failed connections to retryable statuses, has
+    /// already been retried by the middleware for each request `fetch` sends.
+    pub(crate) async fn retry_body<T, F, Fut>(&self, mut fetch: F) -> CanvasResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = CanvasResult<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match fetch().await {
+                Err(CanvasError::Reqwest(e)) if e.is_body() || e.is_decode() => {
+                    let e = CanvasError::Reqwest(e);
+                    if !self.should_retry(&e, attempt) {
+                        return Err(e);
+                    }
+                    tokio::time::sleep(self.delay(&e, attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// How long to wait before attempt number `attempt + 1`.
+    pub(crate) fn delay(&self, error: &CanvasError, attempt: u32) -> Duration {
+        if self.respect_retry_after {
+            if let Some(retry_after) = error.api_error().and_then(|e| e.retry_after) {
+                return retry_after;
+            }
+        }
+
+        let exponent = attempt.saturating_sub(1) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        Duration::from_secs_f64(backoff * (1.0 - jitter * fastrand::f64()))
+    }
+}
+
+impl Middleware for RetryPolicy {
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, CanvasResult<Response>> {
+        Box::pin(async move {
+            if !req.method().is_idempotent() {
+                return next.run(req).await;
+            }
+
+            let mut attempt = 1;
+            loop {
+                // Requests with a streaming body cannot be sent twice.
+                let Some(retry) = req.try_clone() else {
+                    return next.run(req).await;
+                };
+                match next.run(retry).await {
+                    Err(e) if self.should_retry(&e, attempt) => {
+                        tokio::time::sleep(self.delay(&e, attempt)).await;
+                        attempt += 1;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+}
+
+/// Parse a `Retry-After` header, given either in seconds or as an HTTP date.
+pub(crate) fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let wait = date - OffsetDateTime::now_utc();
+    Some(wait.try_into().unwrap_or_default())
+}
@@ -0,0 +1,193 @@
+//! Instrumentation through the `tracing` crate, enabled by the `tracing`
+//! feature.
+//!
+//! Every attempt of a request runs in a `canvas.request` span and every page
+//! of a paginated endpoint in a `canvas.page` span. Headers are never
+//! recorded, and `access_token` query parameters are redacted from URLs.
+use crate::{
+    error::CanvasError,
+    middleware::{Middleware, Next},
+    CanvasResult,
+};
+
+use futures::future::BoxFuture;
+use reqwest::{header::HeaderMap, Request, Response};
+use std::time::Instant;
+use tracing::{field::Empty, Instrument};
+use url::Url;
+
+const COST_HEADER: &str = "x-request-cost";
+const REMAINING_HEADER: &str = "x-rate-limit-remaining";
+
+/// Records a span for every request, right inside the retries so that each
+/// attempt gets its own.
+pub(crate) struct Trace;
+
+impl Middleware for Trace {
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, CanvasResult<Response>> {
+        let span = tracing::debug_span!(
+            "canvas.request",
+            method = %req.method(),
+            endpoint = req.url().path(),
+            url = %redact_url(req.url()),
+            status = Empty,
+            latency_ms = Empty,
+            cost = Empty,
+            rate_limit_remaining = Empty,
+        );
+        let record = span.clone();
+
+        Box::pin(
+            async move {
+                let started = Instant::now();
+                let result = next.run(req).await;
+                record.record("latency_ms", started.elapsed().as_millis() as u64);
+
+                let headers = match &result {
+                    Ok(resp) => Some(resp.headers()),
+                    Err(e) => e.api_error().map(|e| &e.headers),
+                };
+                if let Some(headers) = headers {
+                    record_rate_limit(&record, headers);
+                }
+                match &result {
+                    Ok(resp) => {
+                        record.record("status", resp.status().as_u16());
+                        tracing::debug!("request succeeded");
+                    }
+                    Err(e) => {
+                        if let Some(status) = e.status() {
+                            record.record("status", status.as_u16());
+                        }
+                        tracing::warn!(error = %describe_error(e), "request failed");
+                    }
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+fn record_rate_limit(span: &tracing::Span, headers: &HeaderMap) {
+    let parse = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<f64>().ok())
+    };
+    if let Some(cost) = parse(COST_HEADER) {
+        span.record("cost", cost);
+    }
+    if let Some(remaining) = parse(REMAINING_HEADER) {
+        span.record("rate_limit_remaining", remaining);
+    }
+}
+
+/// What to log about a failed request. The error messages of reqwest and of
+/// error statuses include the URL, which may carry an access token, so it is
+/// left out; the span records the redacted URL.
+fn describe_error(error: &CanvasError) -> String {
+    if let Some(api_error) = error.api_error() {
+        let mut description = format!("returned {}", api_error.status);
+        if !api_error.messages.is_empty() {
+            description = format!("{description}: {}", api_error.messages.join("; "));
+        }
+        return description;
+    }
+    match error {
+        CanvasError::Reqwest(e) => {
+            let kind = if e.is_timeout() {
+                "request timed out"
+            } else if e.is_connect() {
+                "failed to connect"
+            } else if e.is_body() || e.is_decode() {
+                "failed to read the response body"
+            } else {
+                "failed to send the request"
+            };
+            match std::error::Error::source(e) {
+                Some(source) => format!("{kind}: {source}"),
+                None => kind.to_string(),
+            }
+        }
+        CanvasError::TooManyRedirects(_) => "too many redirects".to_string(),
+        error => error.to_string(),
+    }
+}
+
+/// The span a page of a paginated endpoint is fetched in. `items` and
+/// `has_next` are recorded once it has been decoded.
+pub(crate) fn page_span(url: &str) -> tracing::Span {
+    let url = Url::parse(url).map_or_else(|_| "<invalid URL>".to_string(), |url| redact_url(&url));
+    tracing::debug_span!(
+        "canvas.page",
+        url = %url,
+        items = Empty,
+        has_next = Empty,
+    )
+}
+
+/// `url` with the value of any `access_token` query parameter replaced.
+pub(crate) fn redact_url(url: &Url) -> String {
+    if !url.query_pairs().any(|(key, _)| key == "access_token") {
+        return url.to_string();
+    }
+    let pairs: Vec<_> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if key == "access_token" {
+                "[REDACTED]".into()
+            } else {
+                value
+            };
+            (key.into_owned(), value.into_owned())
+        })
+        .collect();
+    let mut redacted = url.clone();
+    redacted.query_pairs_mut().clear().extend_pairs(pairs);
+    redacted.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::{Method, ResponseBuilderExt};
+
+    const URL: &str = "http://127.0.0.1:1/api/v1/courses?access_token=secret";
+
+    #[tokio::test]
+    async fn error_statuses_are_logged_without_the_url() {
+        let resp = http::Response::builder()
+            .status(404)
+            .url(Url::parse(URL).unwrap())
+            .body(r#"{"errors":[{"message":"The specified resource does not exist."}]}"#)
+            .unwrap();
+        let error = CanvasError::from_response(Method::GET, resp.into()).await;
+        assert!(error.to_string().contains("secret"));
+        assert_eq!(
+            describe_error(&error),
+            "returned 404 Not Found: The specified resource does not exist."
+        );
+    }
+
+    #[tokio::test]
+    async fn transport_errors_are_logged_without_the_url() {
+        let error = CanvasError::from(reqwest::get(URL).await.unwrap_err());
+        assert!(error.to_string().contains("secret"));
+        let description = describe_error(&error);
+        assert!(
+            description.starts_with("failed to connect"),
+            "{description}"
+        );
+        assert!(!description.contains("secret"));
+    }
+
+    #[test]
+    fn access_tokens_are_redacted() {
+        assert_eq!(
+            redact_url(&Url::parse(URL).unwrap()),
+            "http://127.0.0.1:1/api/v1/courses?access_token=%5BREDACTED%5D"
+        );
+    }
+}